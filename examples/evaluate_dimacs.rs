@@ -3,9 +3,11 @@ extern crate itertools;
 extern crate smellysat;
 
 use itertools::Itertools;
-use log::info;
-use smellysat::dimacs;
-use std::{env, process};
+use smellysat::{
+    dimacs,
+    solver::{ProofFormat, SolveResult},
+};
+use std::{env, fs::File, io, process};
 
 use thiserror::Error;
 
@@ -13,47 +15,60 @@ use thiserror::Error;
 enum Error {
     #[error("failed to parse input")]
     ParsingError(#[from] dimacs::DimacsError),
+    #[error("failed to write proof")]
+    ProofError(#[from] io::Error),
 }
 
 fn main() {
     env_logger::init();
 
     let args: Vec<String> = env::args().collect();
-    let filepath = match args.len() {
-        2 => args.get(1).unwrap(),
+    let (filepath, proof_path) = match args.len() {
+        2 => (args.get(1).unwrap(), None),
+        3 => (args.get(1).unwrap(), args.get(2)),
         _ => {
-            eprintln!("c evaluate_dimacs [path to problem file]");
+            eprintln!(
+                "c evaluate_dimacs [path to problem file] [path to write DRAT, or .lrat, proof to]"
+            );
             process::exit(-1);
         }
     };
-    match run(filepath) {
-        Err(err) => {
-            eprintln!("c {}", err);
-            eprintln!("c execution failed");
-            process::exit(-1);
-        }
-        Ok(()) => return,
+    if let Err(err) = run(filepath, proof_path) {
+        eprintln!("c {}", err);
+        eprintln!("c execution failed");
+        process::exit(-1);
     }
 }
 
-fn run(filepath: &str) -> Result<(), Error> {
+fn run(filepath: &str, proof_path: Option<&String>) -> Result<(), Error> {
     let mut instance = dimacs::parse(filepath)?;
+    if let Some(proof_path) = proof_path {
+        let format = match proof_path.ends_with(".lrat") {
+            true => ProofFormat::Lrat,
+            false => ProofFormat::Drat,
+        };
+        instance.set_proof(File::create(proof_path)?, format);
+    }
 
     eprintln!("c evaluating");
     let sol = instance.solve();
-    match sol.assignments() {
-        None => println!("s UNSATISFIABLE"),
-        Some(assignment_set) => {
+    match &sol.result {
+        SolveResult::Unsat => println!("s UNSATISFIABLE"),
+        SolveResult::Unknown(_) => println!("s UNKNOWN"),
+        SolveResult::InvalidModel { error, .. } => {
+            eprintln!("c invalid model: {}", error);
+            println!("s UNKNOWN");
+        }
+        SolveResult::Sat(model) => {
             println!("s SATISFIABLE");
 
-            let mut by_var_name = assignment_set
-                .iter()
-                .map(|lit| (sol.literals.get(lit.var()), lit.polarity()))
-                .collect::<Vec<_>>();
-            by_var_name.sort_by_key(|e| e.0.parse::<u64>().unwrap());
-
-            let formatted_vars = by_var_name.iter().map(|(var_name, polarity)| {
-                format!("{}{}", if *polarity { "" } else { "-" }, var_name)
+            // Variable n in the problem file is parsed as Variable(n - 1)
+            let formatted_vars = model.iter().map(|lit| {
+                format!(
+                    "{}{}",
+                    if lit.polarity() { "" } else { "-" },
+                    lit.var().0 + 1
+                )
             });
             let solution =
                 Itertools::intersperse(formatted_vars, " ".to_string()).collect::<String>();
@@ -61,5 +76,6 @@ fn run(filepath: &str) -> Result<(), Error> {
         }
     }
     eprintln!("c stats {:?}", sol.stats);
+    instance.finish_proof()?;
     Ok(())
 }
@@ -42,7 +42,7 @@ fn main() {
         pb.require(
             constraints
                 .iter()
-                .map(|x| *x)
+                .copied()
                 .reduce(|a, b| pb.or(a, b))
                 .unwrap(),
         )
@@ -30,10 +30,7 @@ fn main() {
     for child in &children {
         pb.require(or_list(
             &pb,
-            &by_child[child]
-                .values()
-                .map(|x| *x)
-                .collect::<Vec<BoolExpr>>(),
+            &by_child[child].values().copied().collect::<Vec<BoolExpr>>(),
         ))
     }
 
@@ -55,30 +52,33 @@ fn main() {
     let mut instance = pb.build();
     let result = instance.solve();
     println!("{:?}", result);
+
+    let plans = instance.enumerate().count();
+    println!("{} seating plans in total", plans);
 }
 
-fn or_list(pb: &ProblemBuilder, xs: &Vec<BoolExpr>) -> BoolExpr {
+fn or_list(pb: &ProblemBuilder, xs: &[BoolExpr]) -> BoolExpr {
     match xs.len() {
         0 => panic!("Cannot or empty list"),
         1 => xs[0],
         _ => {
             let mut acc = xs[0];
-            for i in 1..xs.len() {
-                acc = pb.or(acc, xs[i])
+            for &x in &xs[1..] {
+                acc = pb.or(acc, x)
             }
             acc
         }
     }
 }
 
-fn and_list(pb: &ProblemBuilder, xs: &Vec<BoolExpr>) -> BoolExpr {
+fn and_list(pb: &ProblemBuilder, xs: &[BoolExpr]) -> BoolExpr {
     match xs.len() {
         0 => panic!("Cannot or empty list"),
         1 => xs[0],
         _ => {
             let mut acc = xs[0];
-            for i in 1..xs.len() {
-                acc = pb.and(acc, xs[i])
+            for &x in &xs[1..] {
+                acc = pb.and(acc, x)
             }
             acc
         }
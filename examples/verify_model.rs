@@ -0,0 +1,34 @@
+extern crate smellysat;
+
+use smellysat::{dimacs, solver::ModelError};
+use std::{env, process};
+
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+enum Error {
+    #[error("failed to parse input")]
+    ParsingError(#[from] dimacs::DimacsError),
+    #[error("invalid model: {0}")]
+    InvalidModel(#[from] ModelError),
+}
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    if args.len() != 3 {
+        eprintln!("c verify_model [path to problem file] [path to solver output with v lines]");
+        process::exit(-1);
+    }
+    if let Err(err) = run(&args[1], &args[2]) {
+        eprintln!("c {}", err);
+        process::exit(1);
+    }
+    println!("c model verified");
+}
+
+fn run(problem_path: &str, model_path: &str) -> Result<(), Error> {
+    let instance = dimacs::parse(problem_path)?;
+    let model = dimacs::parse_model(model_path)?;
+    instance.verify_model(&model)?;
+    Ok(())
+}
@@ -44,7 +44,7 @@ pub fn parse(filename: &str) -> Result<Instance> {
             Err(err) => vec![Err(err.into())],
         });
 
-    let _header = DimacsHeader::parse(&mut words)?;
+    let header = DimacsHeader::parse(&mut words)?;
 
     let mut cnf: Vec<Clause> = vec![];
     let mut current_clause: Vec<Literal> = vec![];
@@ -53,7 +53,7 @@ pub fn parse(filename: &str) -> Result<Instance> {
     for mb_word in words {
         match mb_word?.parse::<i64>()? {
             0 => {
-                cnf.push(Clause::new(&current_clause));
+                cnf.push(Clause::new_with_id(cnf.len(), &current_clause));
                 current_clause.clear();
             }
             encoded_value => {
@@ -63,18 +63,55 @@ pub fn parse(filename: &str) -> Result<Instance> {
                 } else {
                     -encoded_value
                 } as u64;
-                let var = vars.ensure_original(&value.to_string());
-                current_clause.push(Literal::new(var, polarity));
+                current_clause.push(Literal::new(dimacs_variable(&mut vars, value), polarity));
             }
         }
     }
 
+    // Variables declared but never used still count towards the models
+    while (vars.count() as u64) < header.var_count {
+        vars.create_original(&(vars.count() + 1).to_string());
+    }
     Ok(Instance::new_from_clauses(cnf, vars))
 }
 
+/// Reads a model in the competition output format: `v` lines listing the true literals,
+/// terminated by 0. `s` and `c` lines are skipped.
+pub fn parse_model(filename: &str) -> Result<Vec<Literal>> {
+    let file = File::open(filename)?;
+    let buffer = BufReader::new(&file);
+
+    let mut model = vec![];
+    for line in buffer.lines() {
+        let line = line?;
+        let mut words = line.split_ascii_whitespace();
+        match words.next() {
+            None | Some("c") | Some("s") => continue,
+            Some("v") => {}
+            Some(_) => return Err(DimacsError::InvalidLine(line)),
+        }
+        for word in words {
+            match word.parse::<i64>()? {
+                0 => return Ok(model),
+                value => model.push(Literal::new(Variable(value.unsigned_abs() - 1), value > 0)),
+            }
+        }
+    }
+    Ok(model)
+}
+
+/// Variable n in the file is always Variable(n - 1), so that models and proofs can be written
+/// back out with the same numbering
+fn dimacs_variable(vars: &mut VariableRegister, value: u64) -> Variable {
+    while (vars.count() as u64) < value {
+        vars.create_original(&(vars.count() + 1).to_string());
+    }
+    Variable(value - 1)
+}
+
 #[derive(Debug, Clone)]
 struct DimacsHeader {
-    _var_count: u64,
+    var_count: u64,
     _clause_count: u64,
 }
 
@@ -96,7 +133,7 @@ impl DimacsHeader {
         let var_count = next()?;
         let clause_count = next()?;
         Ok(Self {
-            _var_count: var_count.parse::<u64>()?,
+            var_count: var_count.parse::<u64>()?,
             _clause_count: clause_count.parse::<u64>()?,
         })
     }
@@ -320,6 +357,80 @@ mod tests {
         assert_eq!(instance.variables.count(), 5);
     }
 
+    #[test]
+    fn test_parse_unused_variables() {
+        let content = "p cnf 4 1\n1 -2 0\n";
+        let file = write_temp_cnf(content);
+
+        let instance = parse(file.path().to_str().unwrap()).expect("Failed to parse");
+
+        assert_eq!(instance.variables.count(), 4);
+        assert_eq!(
+            instance.count_models().unwrap(),
+            crate::solver::BigUint::from(12u32)
+        );
+    }
+
+    #[test]
+    fn test_parse_model() {
+        let content = "c solved\ns SATISFIABLE\nv 1 -2\nv 3 0\n";
+        let file = write_temp_cnf(content);
+
+        let model = parse_model(file.path().to_str().unwrap()).expect("Failed to parse");
+
+        assert_eq!(
+            model,
+            vec![
+                Literal::new(Variable(0), true),
+                Literal::new(Variable(1), false),
+                Literal::new(Variable(2), true),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_verify_parsed_model() {
+        let instance = parse("examples/problem_specs/sat/aim-50-1_6-yes1-4.cnf")
+            .expect("Failed to parse SAT benchmark");
+        let mut solver = instance.clone();
+        let lits = solver.solve().assignments().unwrap();
+        let dimacs_lits = lits
+            .iter()
+            .map(|lit| {
+                let value = lit.var().idx() as i64 + 1;
+                if lit.polarity() {
+                    value.to_string()
+                } else {
+                    (-value).to_string()
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(" ");
+        let file = write_temp_cnf(&format!("s SATISFIABLE\nv {} 0\n", dimacs_lits));
+
+        let model = parse_model(file.path().to_str().unwrap()).expect("Failed to parse");
+        assert_eq!(instance.verify_model(&model), Ok(()));
+
+        // Falsify the first clause, whatever the rest of the model says
+        let falsified = instance.clauses[0].literals().clone();
+        let broken = model
+            .iter()
+            .map(
+                |lit| match falsified.iter().find(|f| f.var() == lit.var()) {
+                    Some(f) => f.invert(),
+                    None => *lit,
+                },
+            )
+            .collect::<Vec<_>>();
+        match instance.verify_model(&broken) {
+            Err(crate::solver::ModelError::UnsatisfiedClause { clause, literals }) => {
+                assert_eq!(clause, 0);
+                assert_eq!(&literals, &falsified);
+            }
+            other => panic!("expected an unsatisfied clause, got {:?}", other),
+        }
+    }
+
     // Integration tests with real benchmark files
     #[test]
     fn test_parse_real_sat_file() {
@@ -338,7 +449,7 @@ mod tests {
 
         // dubois20 should have specific structure
         assert!(!instance.clauses.is_empty());
-        assert!(!instance.variables.iter().next().is_none());
+        assert!(instance.variables.iter().next().is_some());
     }
 
     #[test]
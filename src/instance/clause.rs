@@ -34,6 +34,7 @@ impl std::cmp::Ord for Clause {
 }
 
 impl Clause {
+    #[allow(dead_code)]
     pub(crate) fn new(lits: &[Literal]) -> Clause {
         Self::new_with_id(0, lits)
     }
@@ -56,6 +57,10 @@ impl Clause {
         clause
     }
 
+    pub(crate) fn id(&self) -> usize {
+        self.id
+    }
+
     pub(crate) fn len(&self) -> usize {
         self.literals.len()
     }
@@ -35,6 +35,11 @@ impl Literal {
     pub fn invert(&self) -> Literal {
         Literal(self.0 ^ 1)
     }
+
+    /// A dense index for the literal, suitable for indexing per-literal tables
+    pub(crate) fn idx(&self) -> usize {
+        self.0 as usize
+    }
 }
 
 #[cfg(test)]
@@ -43,13 +48,13 @@ mod test {
 
     #[test]
     fn test_literal_bookkeeping() {
-        for idx in vec![0, 10000000, 1000, 1 << 46] {
+        for idx in [0, 10000000, 1000, 1 << 46] {
             let var = Variable(idx);
             let lit = Literal::new(var, true);
             assert_eq!(lit.var(), var);
             assert_eq!(lit.invert().var(), var);
-            assert_eq!(lit.polarity(), true);
-            assert_eq!(lit.invert().polarity(), false);
+            assert!(lit.polarity());
+            assert!(!lit.invert().polarity());
         }
     }
 }
@@ -133,7 +133,7 @@ mod test {
         let mut pb = ProblemBuilder::new();
 
         let children = vec!["laurie", "lucy", "eric", "rita"];
-        let seats = vec!["a", "b", "c", "d"];
+        let seats = ["a", "b", "c", "d"];
 
         let mut by_child = HashMap::new();
         let mut by_seat = HashMap::new();
@@ -155,26 +155,23 @@ mod test {
         for child in &children {
             pb.require(or_list(
                 &pb,
-                &by_child[child]
-                    .values()
-                    .map(|x| *x)
-                    .collect::<Vec<BoolExpr>>(),
+                &by_child[child].values().copied().collect::<Vec<BoolExpr>>(),
             ))
         }
 
         let mut instance = pb.build();
         let sol = instance.solve();
-        assert!(sol.solution.is_some());
+        assert!(sol.model().is_some());
     }
 
-    fn or_list(pb: &ProblemBuilder, xs: &Vec<BoolExpr>) -> BoolExpr {
+    fn or_list(pb: &ProblemBuilder, xs: &[BoolExpr]) -> BoolExpr {
         match xs.len() {
             0 => panic!("Cannot or empty list"),
             1 => xs[0],
             _ => {
                 let mut acc = xs[0];
-                for i in 1..xs.len() {
-                    acc = pb.or(acc, xs[i])
+                for &x in &xs[1..] {
+                    acc = pb.or(acc, x)
                 }
                 acc
             }
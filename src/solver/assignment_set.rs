@@ -1,26 +1,37 @@
 use crate::instance::*;
 use core::fmt;
 
-use fnv::FnvHashMap;
-
-#[derive(Clone, PartialEq, Eq)]
+/// The value of each assigned variable, indexed by the variable
+#[derive(Clone, Eq)]
 pub(crate) struct LiteralSet {
-    values: FnvHashMap<Variable, bool>,
+    values: Vec<Option<bool>>,
+    size: usize,
 }
 
 impl LiteralSet {
     pub(crate) fn new() -> LiteralSet {
         LiteralSet {
-            values: FnvHashMap::default(),
+            values: vec![],
+            size: 0,
         }
     }
 
     pub(crate) fn add(&mut self, lit: Literal) {
-        self.values.insert(lit.var(), lit.polarity());
+        let idx = lit.var().idx();
+        if idx >= self.values.len() {
+            self.values.resize(idx + 1, None);
+        }
+        if self.values[idx].replace(lit.polarity()).is_none() {
+            self.size += 1;
+        }
     }
 
     pub(crate) fn get(&self, var: Variable) -> Option<Literal> {
-        self.values.get(&var).map(|&x| Literal::new(var, x))
+        self.values
+            .get(var.idx())
+            .copied()
+            .flatten()
+            .map(|x| Literal::new(var, x))
     }
 
     pub(crate) fn contains(&self, lit: Literal) -> bool {
@@ -28,18 +39,22 @@ impl LiteralSet {
     }
 
     pub(crate) fn contains_var(&self, var: Variable) -> bool {
-        self.values.contains_key(&var)
+        self.get(var).is_some()
     }
 
     pub(crate) fn remove(&mut self, lit: Literal) {
-        let removed = self.values.remove(&lit.var());
+        let removed = self
+            .values
+            .get_mut(lit.var().idx())
+            .and_then(|value| value.take());
         if removed != Some(lit.polarity()) {
             panic!("removed different value from entry set: {:?}", lit)
         }
+        self.size -= 1;
     }
 
     pub(crate) fn size(&self) -> usize {
-        self.values.len()
+        self.size
     }
 
     #[allow(dead_code)]
@@ -52,102 +67,33 @@ impl LiteralSet {
     }
 
     pub(crate) fn as_assignment_vec(&self) -> Vec<Literal> {
+        self.iter().collect()
+    }
+
+    fn iter(&self) -> impl Iterator<Item = Literal> + '_ {
         self.values
             .iter()
-            .map(|(k, v)| Literal::new(*k, *v))
-            .collect()
+            .enumerate()
+            .filter_map(|(ix, value)| value.map(|v| Literal::new(Variable(ix as u64), v)))
     }
+}
 
-    pub(crate) fn evaluate<T>(&self, clause: T) -> EvaluationResult
-    where
-        T: Iterator<Item = Literal>,
-    {
-        for literal in clause {
-            if let Some(ass) = self.get(literal.var()) {
-                if ass == literal {
-                    return EvaluationResult::True;
-                }
-            } else {
-                return EvaluationResult::Unknown;
-            }
-        }
-        EvaluationResult::False
+impl PartialEq for LiteralSet {
+    // Sets with the same literals are equal, however far they grew
+    fn eq(&self, other: &LiteralSet) -> bool {
+        self.size == other.size && self.iter().all(|lit| other.contains(lit))
     }
 }
 
 impl fmt::Debug for LiteralSet {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let mut keys = self.values.keys().collect::<Vec<_>>();
-        keys.sort();
-        for key in &keys {
-            write!(f, "{:?}={:?}", key, self.values[key])?;
-            if key != keys.last().unwrap() {
+        let literals = self.as_assignment_vec();
+        for lit in &literals {
+            write!(f, "{:?}={:?}", lit.var(), lit.polarity())?;
+            if lit != literals.last().unwrap() {
                 write!(f, ", ")?;
             }
         }
         Ok(())
     }
 }
-
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
-pub(crate) enum EvaluationResult {
-    True,
-    False,
-    Unknown,
-}
-
-#[cfg(test)]
-mod test {
-    use crate::{
-        instance::*,
-        solver::assignment_set::{EvaluationResult, LiteralSet},
-    };
-
-    #[test]
-    fn test_evaluate_clause_true() {
-        let a = Variable(0);
-        let b = Variable(1);
-        let c = Variable(2);
-        // A OR !C
-        let clause = vec![Literal::new(a, true), Literal::new(c, false)];
-        // a = true, b = true, c = false
-        assert_eq!(
-            LiteralSet::from_assignment_vec(&vec![
-                Literal::new(a, true),
-                Literal::new(b, true),
-                Literal::new(c, false),
-            ])
-            .evaluate(clause.iter().cloned()),
-            EvaluationResult::True
-        );
-        // a = false, b = false, c = false
-        assert_eq!(
-            LiteralSet::from_assignment_vec(&vec![
-                Literal::new(a, false),
-                Literal::new(b, false),
-                Literal::new(c, false),
-            ])
-            .evaluate(clause.iter().cloned()),
-            EvaluationResult::True
-        );
-        // a = false, b = false, c = true
-        assert_eq!(
-            LiteralSet::from_assignment_vec(&vec![
-                Literal::new(a, false),
-                Literal::new(b, false),
-                Literal::new(c, true),
-            ])
-            .evaluate(clause.iter().cloned()),
-            EvaluationResult::False
-        )
-    }
-
-    #[test]
-    fn test_evaluate_clause_missing() {
-        let c = vec![Literal::new(Variable(0), true)];
-        assert_eq!(
-            LiteralSet::new().evaluate(c.iter().cloned()),
-            EvaluationResult::Unknown
-        )
-    }
-}
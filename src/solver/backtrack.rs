@@ -1,4 +1,7 @@
-use fnv::FnvHashSet;
+use std::cmp::Reverse;
+use std::sync::Arc;
+
+use fnv::{FnvHashMap, FnvHashSet};
 use itertools::Itertools;
 use log::trace;
 
@@ -13,18 +16,64 @@ use super::{
     trail::{Trail, TrailEntry},
 };
 
+/// A clause that propagation found to be falsified
 #[derive(Debug, Clone, PartialEq, Eq)]
-pub(crate) struct Conflict {
+pub struct Conflict {
     pub(crate) conflicting_decision: Option<Literal>,
     pub(crate) conflicting_literal: Literal,
     pub(crate) conflicting_clause: ClauseRef,
 }
 
+impl Conflict {
+    /// The literal whose propagation falsified the clause
+    pub fn literal(&self) -> Literal {
+        self.conflicting_literal
+    }
+
+    /// The decision of the level the conflict happened at. None at the root
+    pub fn decision(&self) -> Option<Literal> {
+        self.conflicting_decision
+    }
+}
+
+/// What the solver learnt from a conflict
 #[derive(Debug, Clone, Eq, PartialEq)]
-pub(crate) struct AnalyzedConflict {
+pub struct AnalyzedConflict {
     pub(crate) unique_implication_point: Variable,
     pub(crate) learnt_clause: Vec<Literal>,
     pub(crate) second_highest_decision_level: usize,
+    /// The literal block distance of the learnt clause - how many distinct decision levels its
+    /// literals were assigned in. Lower is better.
+    pub(crate) lbd: usize,
+    /// Every variable that was part of the cut at some point while finding the UIP
+    pub(crate) involved_variables: Vec<Variable>,
+    /// The clauses resolved together to derive the learnt clause, starting with the conflict
+    pub(crate) antecedents: Vec<ClauseRef>,
+    /// How many literals minimization removed from the learnt clause
+    pub(crate) minimized_literal_count: usize,
+}
+
+impl AnalyzedConflict {
+    /// The clause learnt from the conflict. Its first literal is the one it asserts
+    pub fn learnt_clause(&self) -> &[Literal] {
+        &self.learnt_clause
+    }
+
+    /// The highest decision level of the learnt clause's literals other than those at the
+    /// level of the conflict, where the clause becomes unit. This is where a backjump goes
+    pub fn second_highest_decision_level(&self) -> usize {
+        self.second_highest_decision_level
+    }
+
+    /// The literal block distance of the learnt clause
+    pub fn lbd(&self) -> usize {
+        self.lbd
+    }
+
+    /// The variable of the first unique implication point
+    pub fn unique_implication_point(&self) -> Variable {
+        self.unique_implication_point
+    }
 }
 
 #[derive(Default)]
@@ -39,62 +88,220 @@ impl ConflictAnalyzer {
         conflict: &Conflict,
     ) -> Option<AnalyzedConflict> {
         // The first UIP between the conflict and the current decision
-        let (uip, edge) =
+        let (uip, edge, involved_variables, antecedents) =
             self.find_unique_implication_point(clause_store, trail, knowledge_graph, conflict);
         trace!("uip: {:?}", uip);
         trace!("edge: {:?}", edge);
+        let unminimized_len = edge.len();
+        let edge = self.minimize(clause_store, trail, knowledge_graph, uip, edge);
+        trace!("minimized edge: {:?}", edge);
 
         // Find the 2nd last decision level (where we will backtrack to)
         let current_decision_level = trail.current_decision_level();
         trace!("current level: {:?}", current_decision_level);
         let decision_levels = edge
             .iter()
-            .map(|l| {
-                let decision = knowledge_graph.vertex(l.var()).decision.unwrap_or(l.var());
-                trace!("decision: {decision:?}");
-                // If we cannot find an explicit decision level for this, it was set in initial
-                // prop (i.e. level 0)
-                trail.find_decision_level(decision).unwrap_or(0)
-            })
+            .map(|l| trail.find_decision_level(l.var()).unwrap())
             .collect_vec();
         trace!("levels: {:?}", decision_levels);
-        let second_highest_decision_level = edge
+        let second_highest_decision_level = decision_levels
             .iter()
-            .map(|l| {
-                let decision = knowledge_graph.vertex(l.var()).decision.unwrap_or(l.var());
-                // If we cannot find an explicit decision level for this, it was set in initial
-                // prop (i.e. level 0)
-                trail.find_decision_level(decision).unwrap_or(0)
-            })
+            .copied()
             .filter(|&l| l != current_decision_level)
-            .min()
+            .max()
             // If we had no other levels, we need to backtrack to root
             .unwrap_or(0);
         trace!(
             "second highest decision level: {:?}",
             second_highest_decision_level
         );
+        let lbd = decision_levels.iter().unique().count();
 
-        // The clause is the inversion of the edge
-        let clause = edge.iter().map(|l| l.invert()).collect_vec();
+        // The clause is the inversion of the edge. Its literals go from the most recently
+        // assigned, so that it starts with the literal it asserts, then the one to watch with it
+        let clause = edge
+            .iter()
+            .zip(decision_levels.iter())
+            .sorted_by_key(|&(_, &level)| Reverse(level))
+            .map(|(l, _)| l.invert())
+            .collect_vec();
 
         Some(AnalyzedConflict {
             unique_implication_point: uip,
             learnt_clause: clause,
             second_highest_decision_level,
+            lbd,
+            involved_variables,
+            antecedents,
+            minimized_literal_count: unminimized_len - edge.len(),
         })
     }
 
-    /// Returns both the unique implication point AND the cut edge
+    /// The clauses that derive the clause from the conflict by unit propagation, in the order
+    /// they become unit once the clause is assumed false. These are the reasons for every
+    /// assignment the conflict depends on, other than those of the clause's own variables, in
+    /// the order they were made, followed by the conflicting clause. Assignments at the root
+    /// without a reason come from unit clauses. This is the hint that LRAT proofs need.
+    pub(crate) fn resolution_chain(
+        &self,
+        clause_store: &ClauseStore,
+        trail: &Trail,
+        knowledge_graph: &KnowledgeGraph,
+        conflicting_clause: ClauseRef,
+        clause: &[Literal],
+    ) -> Vec<ClauseRef> {
+        let in_clause: FnvHashSet<Variable> = clause.iter().map(|l| l.var()).collect();
+        let mut needed = FnvHashSet::default();
+        let mut stack = clause_store
+            .clause_literals(conflicting_clause)
+            .map(|l| l.var())
+            .collect_vec();
+        while let Some(var) = stack.pop() {
+            if in_clause.contains(&var) || !needed.insert(var) {
+                continue;
+            }
+            if let Some(reason) = knowledge_graph.vertex(var).clause {
+                stack.extend(clause_store.clause_literals(reason).map(|l| l.var()));
+            }
+        }
+        let mut chain = trail
+            .iter()
+            .flat_map(|level| level.iter_literals())
+            .filter(|l| needed.contains(&l.var()))
+            .map(|&l| {
+                knowledge_graph
+                    .vertex(l.var())
+                    .clause
+                    .unwrap_or(ClauseRef::Unit(l))
+            })
+            .collect_vec();
+        chain.push(conflicting_clause);
+        chain
+    }
+
+    /// Traces the assignment of the literal back to the decisions that implied it, following
+    /// the reasons of every inference above the root
+    pub(crate) fn analyse_final(
+        &self,
+        clause_store: &ClauseStore,
+        trail: &Trail,
+        knowledge_graph: &KnowledgeGraph,
+        literal: Literal,
+    ) -> Vec<Literal> {
+        let mut seen = FnvHashSet::default();
+        seen.insert(literal.var());
+        let mut decisions = vec![];
+        for level in trail.iter().skip(1).rev() {
+            for &current in level.iter_literals().rev() {
+                if !seen.contains(&current.var()) {
+                    continue;
+                }
+                match knowledge_graph.vertex(current.var()).clause {
+                    None => decisions.push(current),
+                    Some(clause) => seen.extend(
+                        clause_store
+                            .clause_literals(clause)
+                            .map(|l| l.var())
+                            .filter(|&v| trail.find_decision_level(v) != Some(0)),
+                    ),
+                }
+            }
+        }
+        decisions
+    }
+
+    /// Drops the literals of the cut edge that are implied by the rest of it, as in MiniSat. A
+    /// literal is redundant if every other literal of the clause it was inferred from is in the
+    /// edge, was assigned at the root, or is itself redundant.
+    fn minimize(
+        &self,
+        clause_store: &ClauseStore,
+        trail: &Trail,
+        knowledge_graph: &KnowledgeGraph,
+        uip: Variable,
+        edge: Vec<Literal>,
+    ) -> Vec<Literal> {
+        let in_edge: FnvHashSet<Variable> = edge.iter().map(|l| l.var()).collect();
+        let mut redundant = FnvHashMap::default();
+        edge.into_iter()
+            .filter(|l| {
+                l.var() == uip
+                    || !self.is_redundant(
+                        clause_store,
+                        trail,
+                        knowledge_graph,
+                        &in_edge,
+                        &mut redundant,
+                        l.var(),
+                    )
+            })
+            .collect()
+    }
+
+    /// Walks the reasons of the variable depth first. Results are cached in `redundant`, for
+    /// every variable visited along the way.
+    fn is_redundant(
+        &self,
+        clause_store: &ClauseStore,
+        trail: &Trail,
+        knowledge_graph: &KnowledgeGraph,
+        in_edge: &FnvHashSet<Variable>,
+        redundant: &mut FnvHashMap<Variable, bool>,
+        var: Variable,
+    ) -> bool {
+        let reason_literals = |var: Variable| {
+            knowledge_graph
+                .vertex(var)
+                .clause
+                .map(|clause| clause_store.clause_literals(clause).collect_vec())
+        };
+        let Some(literals) = reason_literals(var) else {
+            // Decisions are never redundant
+            return false;
+        };
+        // Each frame is a variable, the literals of its reason, and how far through them we are
+        let mut stack = vec![(var, literals, 0)];
+        while let Some((current, literals, ix)) = stack.last_mut() {
+            let Some(next) = literals.get(*ix).map(|l| l.var()) else {
+                redundant.insert(*current, true);
+                stack.pop();
+                continue;
+            };
+            *ix += 1;
+            if next == *current
+                || in_edge.contains(&next)
+                || trail.find_decision_level(next) == Some(0)
+                || redundant.get(&next) == Some(&true)
+            {
+                continue;
+            }
+            match (redundant.get(&next), reason_literals(next)) {
+                (None, Some(literals)) => stack.push((next, literals, 0)),
+                _ => {
+                    // We hit a decision, or something already known to be needed. Everything
+                    // on the path here is needed too
+                    redundant.insert(next, false);
+                    for (var, _, _) in stack {
+                        redundant.insert(var, false);
+                    }
+                    return false;
+                }
+            }
+        }
+        true
+    }
+
+    /// Returns the unique implication point, the cut edge, every variable that was in the cut
+    /// along the way, and the clauses that were expanded
     fn find_unique_implication_point(
         &self,
         clause_store: &ClauseStore,
         trail: &Trail,
         knowledge_graph: &KnowledgeGraph,
         conflict: &Conflict,
-    ) -> (Variable, Vec<Literal>) {
+    ) -> (Variable, Vec<Literal>, Vec<Variable>, Vec<ClauseRef>) {
         let assignment = trail.assignment();
-        let current_level_assignments = trail.assignments_since_last_decision();
+        let current_level = trail.current_decision_level();
         // TODO(lcm): consider if this is the right datastructure
         let mut cut_edge = FnvHashSet::default();
         // We start by considering the steps that led us to the conflict. These are the literals
@@ -105,23 +312,29 @@ impl ConflictAnalyzer {
                 .map(|l| l.var()),
         );
         trace!("initial cut: {:?}", cut_edge.iter().collect_vec());
+        let mut involved = cut_edge.clone();
+        let mut antecedents = vec![conflict.conflicting_clause];
 
         let current_decision_level_trail = trail.iter().last().unwrap();
         for current_literal in current_decision_level_trail.iter_literals().rev() {
             let current_vertex = knowledge_graph.vertex(current_literal.var());
             // Remove ourselves from the cut edge, and add the vertices that got us here to
-            // the cut edge.
-            if let Some(clause_ref) = current_vertex.clause {
-                cut_edge.extend(clause_store.clause_literals(clause_ref).map(|l| l.var()));
-                cut_edge.remove(&current_literal.var());
-            } else {
-                // If there's no clause, then we arrived at a decision, and should not replace
-                // ourselves - we are at the UIP
+            // the cut edge. Literals that are not in the cut did not lead to the conflict.
+            // If there's no clause, then we arrived at a decision, and should not replace
+            // ourselves - we are at the UIP
+            match current_vertex.clause {
+                Some(clause_ref) if cut_edge.contains(&current_literal.var()) => {
+                    cut_edge.extend(clause_store.clause_literals(clause_ref).map(|l| l.var()));
+                    cut_edge.remove(&current_literal.var());
+                    involved.extend(cut_edge.iter().copied());
+                    antecedents.push(clause_ref);
+                }
+                _ => {}
             }
 
             let mut current_level_iter = cut_edge
                 .iter()
-                .filter(|&&v| current_level_assignments.contains_var(v));
+                .filter(|&&v| trail.find_decision_level(v) == Some(current_level));
             // There should always be at least one entry in the edge for the current decision level
             let uip = current_level_iter.next().unwrap();
             if current_level_iter.next().is_none() {
@@ -130,15 +343,45 @@ impl ConflictAnalyzer {
                     .map(|&v| assignment.get(v).unwrap())
                     .collect_vec();
                 sort_and_dedupe(&mut edge);
-                return (*uip, edge);
+                let mut involved = involved.into_iter().collect_vec();
+                involved.sort();
+                return (*uip, edge, involved, antecedents);
             }
         }
         panic!("could not find UIP: {:?}", cut_edge);
     }
 }
 
-pub(crate) trait BacktrackStrategy {
-    /// Calculates how far we should roll back the search tree
+/// How far the solver goes back after learning a clause from a conflict
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Backtracking {
+    /// Jump back to the highest decision level where the learnt clause is unit, undoing every
+    /// decision above it
+    #[default]
+    Backjump,
+    /// Chronological backtracking, after Nadel and Ryvchin. When a backjump would undo more
+    /// than `threshold` decision levels, only undo the last one, keeping the rest of the trail.
+    /// This saves redoing much of the same work on problems with long trails
+    Chronological { threshold: usize },
+}
+
+impl Backtracking {
+    pub(crate) fn strategy(&self) -> Arc<dyn BacktrackStrategy> {
+        match *self {
+            Backtracking::Backjump => Arc::new(BackjumpStrategy {}),
+            Backtracking::Chronological { threshold } => {
+                Arc::new(ChronologicalBacktrackStrategy { threshold })
+            }
+        }
+    }
+}
+
+/// Decides how far the solver goes back after each conflict. Implement this to plug your own
+/// strategy in with `SolverConfig::backtrack_strategy`
+pub trait BacktrackStrategy: Send + Sync {
+    /// Calculates how far we should roll back the search tree: the last decision level of the
+    /// path to keep. It must be below the level of the conflict, the last in the path. None
+    /// means there is nowhere to go back to, which is a bug in the strategy
     fn find_backtrack_point(
         &self,
         path: &[TrailEntry],
@@ -185,6 +428,29 @@ impl BacktrackStrategy for BackjumpStrategy {
     }
 }
 
+/// Backjumps, unless that would undo more than the threshold's worth of decision levels, in
+/// which case it undoes only the level of the conflict. The learnt clause's asserting literal
+/// is still implied at the level a backjump would have gone to.
+pub(crate) struct ChronologicalBacktrackStrategy {
+    pub(crate) threshold: usize,
+}
+
+impl BacktrackStrategy for ChronologicalBacktrackStrategy {
+    fn find_backtrack_point(
+        &self,
+        path: &[TrailEntry],
+        _conflict: &Conflict,
+        analyzed_conflict: &AnalyzedConflict,
+    ) -> Option<usize> {
+        let conflict_level = path.len() - 1;
+        let backjump_level = analyzed_conflict.second_highest_decision_level;
+        match conflict_level - backjump_level > self.threshold {
+            true => Some(conflict_level - 1),
+            false => Some(backjump_level),
+        }
+    }
+}
+
 #[cfg(test)]
 mod test {
     use itertools::Itertools;
@@ -194,7 +460,10 @@ mod test {
         *,
     };
 
-    use super::{AnalyzedConflict, Conflict, ConflictAnalyzer};
+    use super::{
+        AnalyzedConflict, BacktrackStrategy, ChronologicalBacktrackStrategy, Conflict,
+        ConflictAnalyzer,
+    };
 
     // Run through the example found here: https://users.aalto.fi/~tjunttil/2020-DP-AUT/notes-sat/cdcl.html#implication-graphs-learned-clauses-and-backjumping
     #[test]
@@ -204,7 +473,7 @@ mod test {
             .map(|i| Literal::new(Variable(i), true))
             .collect_vec();
 
-        let clauses = vec![
+        let clauses = [
             vec![xs[1].invert(), xs[2].invert()],
             vec![xs[1].invert(), xs[3]],
             vec![xs[3].invert(), xs[4].invert()],
@@ -223,6 +492,7 @@ mod test {
         .collect_vec();
 
         let store = clause_store::ClauseStore::new(clauses);
+        let clause_refs = store.iter().collect_vec();
         let mut trail = trail::Trail::new();
         let mut kg = knowledge_graph::KnowledgeGraph::new(13);
 
@@ -231,16 +501,16 @@ mod test {
         kg.add_decision(xs[1]);
         // unit prop with clause 0
         trail.add_inferred(xs[2].invert());
-        kg.add_inferred(xs[2].invert(), xs[1], Some(xs[1]), store.get(0).unwrap());
+        kg.add_inferred(xs[2].invert(), xs[1], Some(xs[1]), clause_refs[0]);
         // unit prop with clause 1
         trail.add_inferred(xs[3]);
-        kg.add_inferred(xs[3], xs[1], Some(xs[1]), store.get(1).unwrap());
+        kg.add_inferred(xs[3], xs[1], Some(xs[1]), clause_refs[1]);
         // unit prop with clause 2
         trail.add_inferred(xs[4].invert());
-        kg.add_inferred(xs[4].invert(), xs[3], Some(xs[1]), store.get(2).unwrap());
+        kg.add_inferred(xs[4].invert(), xs[3], Some(xs[1]), clause_refs[2]);
         // unit prop with clause 3
         trail.add_inferred(xs[5]);
-        kg.add_inferred(xs[5], xs[4].invert(), Some(xs[1]), store.get(3).unwrap());
+        kg.add_inferred(xs[5], xs[4].invert(), Some(xs[1]), clause_refs[3]);
 
         // Now the second decision level (boy this is wordy...)
         trail.add_decision(xs[6].invert());
@@ -251,42 +521,32 @@ mod test {
             xs[7].invert(),
             xs[6].invert(),
             Some(xs[6].invert()),
-            store.get(4).unwrap(),
+            clause_refs[4],
         );
         // Unit prop with clause 5
         trail.add_inferred(xs[8]);
-        kg.add_inferred(
-            xs[8],
-            xs[7].invert(),
-            Some(xs[6].invert()),
-            store.get(5).unwrap(),
-        );
+        kg.add_inferred(xs[8], xs[7].invert(), Some(xs[6].invert()), clause_refs[5]);
         // Unit prop with clause 6
         trail.add_inferred(xs[9].invert());
-        kg.add_inferred(
-            xs[9].invert(),
-            xs[8],
-            Some(xs[6].invert()),
-            store.get(6).unwrap(),
-        );
+        kg.add_inferred(xs[9].invert(), xs[8], Some(xs[6].invert()), clause_refs[6]);
         // Unit prop with clause 7
         trail.add_inferred(xs[10]);
-        kg.add_inferred(xs[10], xs[8], Some(xs[6].invert()), store.get(7).unwrap());
+        kg.add_inferred(xs[10], xs[8], Some(xs[6].invert()), clause_refs[7]);
         // Unit prop with clause 8
         trail.add_inferred(xs[11]);
-        kg.add_inferred(xs[11], xs[10], Some(xs[6].invert()), store.get(8).unwrap());
+        kg.add_inferred(xs[11], xs[10], Some(xs[6].invert()), clause_refs[8]);
         // Unit prop with clause 9
         trail.add_inferred(xs[12].invert());
         kg.add_inferred(
             xs[12].invert(),
             xs[11],
             Some(xs[6].invert()),
-            store.get(9).unwrap(),
+            clause_refs[9],
         );
 
         // Now we have a conflict on clause 10
         let conflict = Conflict {
-            conflicting_clause: store.get(10).unwrap(),
+            conflicting_clause: clause_refs[10],
             conflicting_decision: Some(xs[6].invert()),
             conflicting_literal: xs[12],
         };
@@ -298,7 +558,104 @@ mod test {
                 learnt_clause: vec![xs[8].invert()],
                 unique_implication_point: xs[8].var(),
                 second_highest_decision_level: 0,
+                lbd: 1,
+                involved_variables: (8..13).map(Variable).collect_vec(),
+                antecedents: [10, 9, 8, 7, 6]
+                    .iter()
+                    .map(|&ix| clause_refs[ix])
+                    .collect_vec(),
+                minimized_literal_count: 0,
             })
         );
     }
+
+    #[test]
+    fn test_minimize_learnt_clause() {
+        let xs = (0..6)
+            .map(|i| Literal::new(Variable(i), true))
+            .collect_vec();
+        let clauses = [
+            vec![xs[1].invert(), xs[2]],
+            vec![xs[3].invert(), xs[4]],
+            vec![xs[3].invert(), xs[5]],
+            vec![
+                xs[4].invert(),
+                xs[5].invert(),
+                xs[2].invert(),
+                xs[1].invert(),
+            ],
+        ]
+        .iter()
+        .enumerate()
+        .map(|(id, literals)| Clause::new_with_id(id, literals))
+        .collect_vec();
+
+        let store = clause_store::ClauseStore::new(clauses);
+        let clause_refs = store.iter().collect_vec();
+        let mut trail = trail::Trail::new();
+        let mut kg = knowledge_graph::KnowledgeGraph::new(6);
+
+        trail.add_decision(xs[1]);
+        kg.add_decision(xs[1]);
+        trail.add_inferred(xs[2]);
+        kg.add_inferred(xs[2], xs[1], Some(xs[1]), clause_refs[0]);
+
+        trail.add_decision(xs[3]);
+        kg.add_decision(xs[3]);
+        trail.add_inferred(xs[4]);
+        kg.add_inferred(xs[4], xs[3], Some(xs[3]), clause_refs[1]);
+        trail.add_inferred(xs[5]);
+        kg.add_inferred(xs[5], xs[3], Some(xs[3]), clause_refs[2]);
+
+        let conflict = Conflict {
+            conflicting_clause: clause_refs[3],
+            conflicting_decision: Some(xs[3]),
+            conflicting_literal: xs[5],
+        };
+        let result = ConflictAnalyzer::default()
+            .analyse_conflict(&store, &trail, &kg, &conflict)
+            .unwrap();
+
+        // x2 is implied by x1, so does not need to be in the learnt clause
+        assert_eq!(result.learnt_clause, vec![xs[3].invert(), xs[1].invert()]);
+        assert_eq!(result.minimized_literal_count, 1);
+        assert_eq!(result.second_highest_decision_level, 1);
+        assert_eq!(result.lbd, 2);
+    }
+
+    #[test]
+    fn test_chronological_backtrack_point() {
+        let xs = (0..4)
+            .map(|i| Literal::new(Variable(i), true))
+            .collect_vec();
+        let mut trail = trail::Trail::new();
+        for &x in xs.iter() {
+            trail.add_decision(x);
+        }
+        let conflict = Conflict {
+            conflicting_decision: Some(xs[3]),
+            conflicting_literal: xs[3],
+            conflicting_clause: clause_store::ClauseRef::Pair(xs[0].invert(), xs[3].invert()),
+        };
+        let analyzed_conflict = AnalyzedConflict {
+            unique_implication_point: xs[3].var(),
+            learnt_clause: vec![xs[0].invert(), xs[3].invert()],
+            second_highest_decision_level: 1,
+            lbd: 2,
+            involved_variables: vec![xs[0].var(), xs[3].var()],
+            antecedents: vec![conflict.conflicting_clause],
+            minimized_literal_count: 0,
+        };
+
+        // Backjumping from level 4 to level 1 undoes three levels
+        let find = |threshold| {
+            ChronologicalBacktrackStrategy { threshold }.find_backtrack_point(
+                trail.search_path(),
+                &conflict,
+                &analyzed_conflict,
+            )
+        };
+        assert_eq!(find(3), Some(1));
+        assert_eq!(find(2), Some(3));
+    }
 }
@@ -1,27 +1,85 @@
-#[cfg(debug_assertions)]
-#[allow(unused_imports)]
-use is_sorted::IsSorted;
-use log::info;
+use fnv::{FnvHashMap, FnvHashSet};
+use log::trace;
 use std::hash::Hasher;
 
-use crate::instance::{Clause, Literal, Variable};
+use crate::instance::{Clause, Literal};
 
-use super::clause_index::{ClauseIndex, ClauseIndexView};
+use super::assignment_set::LiteralSet;
+use super::proof::Proof;
+use super::watches::{Watch, WatchUpdate, Watches};
+
+const CLAUSE_ACTIVITY_DECAY: f64 = 0.999;
+const CLAUSE_ACTIVITY_RESCALE_LIMIT: f64 = 1e20;
 
 #[derive(Debug)]
 pub(crate) struct ClauseStore {
     clauses: ClauseList,
-    index: ClauseIndex,
+    watches: Watches,
+    activity_increment: f64,
+    proof: Option<Proof>,
+    // The ID given to the next clause added
+    next_id: u64,
 }
 
 impl ClauseStore {
     pub(crate) fn new(clauses: Vec<Clause>) -> ClauseStore {
         let list = ClauseList::new(clauses);
-        let refs: Vec<ClauseRef> = list.iter().collect();
-        let idx = ClauseIndex::new(&list, &refs);
-        ClauseStore {
+        let next_id = list.meta.iter().map(|meta| meta.id).max().unwrap_or(0) + 1;
+        let mut store = ClauseStore {
             clauses: list,
-            index: idx,
+            watches: Watches::new(),
+            activity_increment: 1.0,
+            proof: None,
+            next_id,
+        };
+        store.rebuild_watches();
+        store
+    }
+
+    /// Records every clause learnt or deleted from now on in the proof
+    pub(crate) fn set_proof(&mut self, proof: Proof) {
+        self.proof = Some(proof);
+    }
+
+    pub(crate) fn proof_mut(&mut self) -> Option<&mut Proof> {
+        self.proof.as_mut()
+    }
+
+    pub(crate) fn has_proof(&self) -> bool {
+        self.proof.is_some()
+    }
+
+    /// Whether learnt clauses must be given the clauses they were derived from
+    pub(crate) fn proof_needs_hints(&self) -> bool {
+        self.proof.as_ref().is_some_and(|proof| proof.needs_hints())
+    }
+
+    /// Records that we derived the empty clause, completing the proof. See `add_learnt_clause`
+    /// for the hints.
+    pub(crate) fn learnt_empty_clause(&mut self, hints: &[ClauseRef]) {
+        let id = self.take_id();
+        let hints = self.hint_ids(hints);
+        if let Some(proof) = self.proof.as_mut() {
+            proof.add(id, &[], &hints);
+        }
+    }
+
+    /// The ID of the clause, as used in proofs
+    pub(crate) fn clause_id(&self, clause: ClauseRef) -> u64 {
+        self.clauses
+            .id_of(clause)
+            .unwrap_or_else(|| panic!("unknown clause: {:?}", clause))
+    }
+
+    fn take_id(&mut self) -> u64 {
+        self.next_id += 1;
+        self.next_id - 1
+    }
+
+    fn hint_ids(&self, hints: &[ClauseRef]) -> Vec<u64> {
+        match self.proof_needs_hints() {
+            true => hints.iter().map(|&clause| self.clause_id(clause)).collect(),
+            false => vec![],
         }
     }
 
@@ -29,32 +87,225 @@ impl ClauseStore {
         self.clauses.iter()
     }
 
-    pub(crate) fn idx(&self) -> ClauseIndexView<'_> {
-        ClauseIndexView::new(self, &self.index)
+    /// Every clause, along with whether it was learnt
+    pub(crate) fn iter_with_meta(&self) -> impl Iterator<Item = (ClauseRef, &ClauseMeta)> + '_ {
+        self.clauses.iter_with_meta()
+    }
+
+    /// Adds a clause, watching its first two literals. When adding a clause to a partial
+    /// assignment, callers must order the literals such that the first two are the best
+    /// candidates to watch - unassigned literals first, then the most recently falsified ones.
+    pub(crate) fn add_clause(&mut self, clause_literals: Vec<Literal>) -> ClauseRef {
+        trace!("added clause: {:?}", clause_literals);
+        let id = self.take_id();
+        let clause = self
+            .clauses
+            .add_clause(clause_literals, ClauseMeta::original(id));
+        self.watch_clause(clause);
+        clause
+    }
+
+    /// Adds a clause derived through conflict analysis. See `add_clause` for how the literals
+    /// should be ordered. If the proof needs them, the hints are the clauses that derive it by
+    /// unit propagation, in the order they become unit.
+    pub(crate) fn add_learnt_clause(
+        &mut self,
+        clause_literals: Vec<Literal>,
+        lbd: usize,
+        hints: &[ClauseRef],
+    ) -> ClauseRef {
+        trace!("learnt clause: {:?}", clause_literals);
+        let id = self.take_id();
+        let hints = self.hint_ids(hints);
+        if let Some(proof) = self.proof.as_mut() {
+            proof.add(id, &clause_literals, &hints);
+        }
+        let clause = self
+            .clauses
+            .add_clause(clause_literals, ClauseMeta::learnt(id, lbd));
+        self.watch_clause(clause);
+        clause
+    }
+
+    /// The clauses we have learnt, along with their scores
+    pub(crate) fn learnt_clauses(&self) -> impl Iterator<Item = (ClauseRef, &ClauseMeta)> + '_ {
+        self.clauses
+            .iter_with_meta()
+            .filter(|(_, meta)| meta.learnt)
+    }
+
+    pub(crate) fn learnt_clause_count(&self) -> usize {
+        self.learnt_clauses().count()
     }
 
-    pub(crate) fn get(&self, ix: usize) -> Option<ClauseRef> {
-        self.clauses.get(ix)
+    /// Marks the clause as useful, as it took part in deriving a learnt clause. Only long
+    /// clauses are ever deleted, so only they are scored
+    pub(crate) fn bump_activity(&mut self, clause: ClauseRef) {
+        if !matches!(clause, ClauseRef::Long { .. }) {
+            return;
+        }
+        let Some(ix) = self.clauses.index_of(clause) else {
+            return;
+        };
+        let meta = &mut self.clauses.meta[ix];
+        if !meta.learnt {
+            return;
+        }
+        meta.activity += self.activity_increment;
+        if meta.activity > CLAUSE_ACTIVITY_RESCALE_LIMIT {
+            for meta in self.clauses.meta.iter_mut() {
+                meta.activity /= CLAUSE_ACTIVITY_RESCALE_LIMIT;
+            }
+            self.activity_increment /= CLAUSE_ACTIVITY_RESCALE_LIMIT;
+        }
+    }
+
+    /// Marks the clause as done with by vivification. Only long learnt clauses are vivified
+    pub(crate) fn mark_vivified(&mut self, clause: ClauseRef) {
+        if !matches!(clause, ClauseRef::Long { .. }) {
+            return;
+        }
+        if let Some(ix) = self.clauses.index_of(clause) {
+            self.clauses.meta[ix].vivified = true;
+        }
+    }
+
+    /// Called once per conflict, so that recent bumps outweigh older ones
+    pub(crate) fn decay_activity(&mut self) {
+        self.activity_increment /= CLAUSE_ACTIVITY_DECAY;
+    }
+
+    /// Deletes the clauses, and compacts the store to reclaim their space. This moves the
+    /// remaining long clauses, so references held elsewhere must be updated with the returned
+    /// mapping from old to new references.
+    pub(crate) fn delete_clauses(
+        &mut self,
+        clauses: &FnvHashSet<ClauseRef>,
+    ) -> FnvHashMap<ClauseRef, ClauseRef> {
+        for &clause in clauses.iter() {
+            if let Some(proof) = self.proof.as_mut() {
+                let literals = clause.literals_from_list(&self.clauses).collect::<Vec<_>>();
+                proof.delete(self.clauses.id_of(clause).unwrap(), &literals);
+            }
+            self.clauses.delete(clause);
+        }
+        let moved = self.clauses.compact();
+        // Watched literals are kept at the start of each clause, so the watches can be
+        // rebuilt as they were
+        self.rebuild_watches();
+        moved
+    }
+
+    fn rebuild_watches(&mut self) {
+        self.watches = Watches::new();
+        let refs: Vec<ClauseRef> = self.iter().collect();
+        for clause in refs {
+            self.watch_clause(clause);
+        }
     }
 
-    #[allow(dead_code)]
-    pub(crate) fn contains(&self, clause: &[Literal]) -> bool {
-        self.clauses.contains(clause)
+    fn watch_clause(&mut self, clause: ClauseRef) {
+        match clause {
+            ClauseRef::Unit(_) => {}
+            ClauseRef::Pair(a, b) => {
+                self.watches.watch(a, Watch { clause, blocker: b });
+                self.watches.watch(b, Watch { clause, blocker: a });
+            }
+            ClauseRef::Long { offset, length: _ } => {
+                let (a, b) = (
+                    self.clauses.literals[offset],
+                    self.clauses.literals[offset + 1],
+                );
+                self.watches.watch(a, Watch { clause, blocker: b });
+                self.watches.watch(b, Watch { clause, blocker: a });
+            }
+        }
     }
 
-    pub(crate) fn mark_resolved(&mut self, var: Variable) {
-        self.index.mark_resolved(var)
+    /// Takes the list of clauses watching the literal. See `Watches::take`
+    pub(crate) fn take_watches(&mut self, literal: Literal) -> Vec<Watch> {
+        self.watches.take(literal)
     }
 
-    pub(crate) fn mark_unresolved(&mut self, var: Variable) {
-        self.index.mark_unresolved(var)
+    pub(crate) fn restore_watches(&mut self, literal: Literal, watches: Vec<Watch>) {
+        self.watches.restore(literal, watches)
     }
 
-    pub(crate) fn add_clause(&mut self, clause_literals: Vec<Literal>) -> Option<ClauseRef> {
-        let clause = self.clauses.add_clause(clause_literals.clone())?;
-        self.index.add_clause(clause, &clause_literals);
-        info!("added clause: {:?}", clause_literals);
-        Some(clause)
+    /// Called when `false_literal`, one of the watched literals of a long clause, has become
+    /// false. Looks for a non-false literal in the rest of the clause to watch instead.
+    pub(crate) fn update_watch(
+        &mut self,
+        clause: ClauseRef,
+        false_literal: Literal,
+        assignment: &LiteralSet,
+    ) -> WatchUpdate {
+        let (offset, length) = match clause {
+            ClauseRef::Long { offset, length } => (offset, length),
+            _ => panic!("only long clauses can move their watches: {:?}", clause),
+        };
+        let literals = &mut self.clauses.literals[offset..(offset + length)];
+        // Keep the literal that became false in the second position
+        if literals[0] == false_literal {
+            literals.swap(0, 1);
+        }
+        let other = literals[0];
+        if assignment.contains(other) {
+            return WatchUpdate::Satisfied(other);
+        }
+        for ix in 2..length {
+            if !assignment.contains(literals[ix].invert()) {
+                literals.swap(1, ix);
+                self.watches.watch(
+                    literals[1],
+                    Watch {
+                        clause,
+                        blocker: other,
+                    },
+                );
+                return WatchUpdate::Moved;
+            }
+        }
+        WatchUpdate::Unit(other)
+    }
+}
+
+/// Bookkeeping for a single clause
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct ClauseMeta {
+    /// Whether the clause was learnt, rather than being part of the problem
+    pub(crate) learnt: bool,
+    /// The literal block distance of the clause when it was learnt
+    pub(crate) lbd: usize,
+    /// How recently the clause has been useful in conflict analysis
+    pub(crate) activity: f64,
+    /// Identifies the clause in proofs
+    pub(crate) id: u64,
+    /// Whether vivification has already tried to shorten the clause
+    pub(crate) vivified: bool,
+    deleted: bool,
+}
+
+impl ClauseMeta {
+    fn original(id: u64) -> ClauseMeta {
+        ClauseMeta {
+            id,
+            learnt: false,
+            lbd: 0,
+            activity: 0.0,
+            vivified: false,
+            deleted: false,
+        }
+    }
+
+    fn learnt(id: u64, lbd: usize) -> ClauseMeta {
+        ClauseMeta {
+            id,
+            learnt: true,
+            lbd,
+            activity: 0.0,
+            vivified: false,
+            deleted: false,
+        }
     }
 }
 
@@ -63,54 +314,111 @@ impl ClauseStore {
 struct ClauseList {
     // We store all the literals in the clauses contiguously
     literals: Vec<Literal>,
-    // And then store the offsets for a particular clause
+    // And then store the offsets for a particular clause. These are always ascending
     offsets: Vec<usize>,
+    // And the bookkeeping for each clause
+    meta: Vec<ClauseMeta>,
+    // The IDs of unit and pair clauses, which cannot be found by offset
+    short_ids: FnvHashMap<ClauseRef, u64>,
 }
 
 impl ClauseList {
     fn new(clauses: Vec<Clause>) -> ClauseList {
-        let mut offsets = Vec::with_capacity(clauses.len());
-        let mut literals = Vec::with_capacity(clauses.iter().map(|cl| cl.len()).sum());
+        let mut list = ClauseList {
+            literals: Vec::with_capacity(clauses.iter().map(|cl| cl.len()).sum()),
+            offsets: Vec::with_capacity(clauses.len()),
+            meta: Vec::with_capacity(clauses.len()),
+            short_ids: FnvHashMap::default(),
+        };
         for clause in clauses.into_iter() {
-            offsets.push(literals.len());
+            // Clause IDs start from 0, but proofs number clauses from 1
+            let id = clause.id() as u64 + 1;
             let mut clause_literals = clause.into_literals();
             clause_literals.sort();
-            literals.extend(clause_literals);
+            list.add_clause(clause_literals, ClauseMeta::original(id));
+        }
+        list
+    }
+
+    /// Adds the clause, preserving the order of its literals
+    fn add_clause(&mut self, clause: Vec<Literal>, meta: ClauseMeta) -> ClauseRef {
+        let offset = self.literals.len();
+        self.offsets.push(offset);
+        let id = meta.id;
+        self.meta.push(meta);
+        let clause_len = clause.len();
+        self.literals.extend(clause);
+        let clause = self.mk_ref(offset, clause_len);
+        if clause_len <= 2 {
+            self.short_ids.entry(clause).or_insert(id);
         }
+        clause
+    }
 
-        ClauseList { literals, offsets }
+    fn iter(&self) -> impl Iterator<Item = ClauseRef> + Captures<'_> {
+        self.iter_with_meta().map(|(clause, _)| clause)
     }
 
-    fn contains(&self, clause: &[Literal]) -> bool {
-        ensure_sorted(clause);
-        // Check we haven't already seen this clause!
-        for cl in self.iter() {
-            if cl.len() != clause.len() {
-                continue;
-            }
-            if cl.literals_from_list(self).eq(clause.iter().copied()) {
-                // This clause is already in the dataset
-                return true;
-            }
+    fn iter_with_meta(&self) -> impl Iterator<Item = (ClauseRef, &ClauseMeta)> + '_ {
+        (0..self.offsets.len())
+            .filter(|&ix| !self.meta[ix].deleted)
+            .map(|ix| (self.get(ix).unwrap(), &self.meta[ix]))
+    }
+
+    /// Finds the index of the clause in the list
+    fn index_of(&self, clause: ClauseRef) -> Option<usize> {
+        match clause {
+            ClauseRef::Long { offset, length: _ } => self.offsets.binary_search(&offset).ok(),
+            _ => (0..self.offsets.len())
+                .find(|&ix| !self.meta[ix].deleted && self.get(ix) == Some(clause)),
         }
-        false
     }
 
-    fn add_clause(&mut self, clause: Vec<Literal>) -> Option<ClauseRef> {
-        if self.contains(&clause) {
-            return None;
+    fn id_of(&self, clause: ClauseRef) -> Option<u64> {
+        match clause {
+            ClauseRef::Long { .. } => self.index_of(clause).map(|ix| self.meta[ix].id),
+            _ => self.short_ids.get(&clause).copied(),
         }
+    }
 
-        // otherwise, add
-        let offset = self.literals.len();
-        self.offsets.push(offset);
-        let clause_len = clause.len();
-        self.literals.extend(clause);
-        Some(self.mk_ref(offset, clause_len))
+    fn delete(&mut self, clause: ClauseRef) {
+        let ix = self
+            .index_of(clause)
+            .unwrap_or_else(|| panic!("deleting unknown clause: {:?}", clause));
+        self.meta[ix].deleted = true;
+        if self.short_ids.get(&clause) == Some(&self.meta[ix].id) {
+            self.short_ids.remove(&clause);
+        }
     }
 
-    fn iter(&self) -> impl Iterator<Item = ClauseRef> + Captures<'_> {
-        (0..self.offsets.len()).map(|ix| self.get(ix).unwrap())
+    /// Drops deleted clauses from the list. Returns where each remaining long clause moved to
+    fn compact(&mut self) -> FnvHashMap<ClauseRef, ClauseRef> {
+        let mut moved = FnvHashMap::default();
+        let mut literals = Vec::with_capacity(self.literals.len());
+        let mut offsets = Vec::with_capacity(self.offsets.len());
+        let mut meta = Vec::with_capacity(self.meta.len());
+        for ix in 0..self.offsets.len() {
+            if self.meta[ix].deleted {
+                continue;
+            }
+            let clause = self.get(ix).unwrap();
+            offsets.push(literals.len());
+            if let ClauseRef::Long { offset: _, length } = clause {
+                moved.insert(
+                    clause,
+                    ClauseRef::Long {
+                        offset: literals.len(),
+                        length,
+                    },
+                );
+            }
+            literals.extend(clause.literals_from_list(self));
+            meta.push(self.meta[ix].clone());
+        }
+        self.literals = literals;
+        self.offsets = offsets;
+        self.meta = meta;
+        moved
     }
 
     fn get(&self, ix: usize) -> Option<ClauseRef> {
@@ -321,8 +629,8 @@ impl<'a> Iterator for ClauseLiteralsIterator<'a> {
             2
         } else if self.fst.is_some() {
             1
-        } else if self.rst.is_some() {
-            self.rst.unwrap().len()
+        } else if let Some(rst) = self.rst {
+            rst.len()
         } else {
             0
         };
@@ -336,16 +644,6 @@ impl<'a> ExactSizeIterator for ClauseLiteralsIterator<'a> {
     }
 }
 
-#[cfg(not(debug_assertions))]
-fn ensure_sorted(_: &[Literal]) {}
-
-#[cfg(debug_assertions)]
-fn ensure_sorted(lits: &[Literal]) {
-    if !lits.iter().is_sorted() {
-        panic!("must be sorted");
-    }
-}
-
 // https://github.com/rust-lang/rust/issues/34511#issuecomment-373423999
 pub trait Captures<'a> {}
 impl<'a, T: ?Sized> Captures<'a> for T {}
@@ -355,8 +653,17 @@ mod test {
     use itertools::Itertools;
 
     use crate::instance::{Clause, Literal, Variable};
+    use crate::solver::assignment_set::LiteralSet;
+    use crate::solver::watches::{Watch, WatchUpdate};
+
+    use super::{ClauseRef, ClauseStore};
 
-    use super::ClauseStore;
+    /// The watches on the literal, left in place
+    fn watches_on(cs: &mut ClauseStore, literal: Literal) -> Vec<Watch> {
+        let watches = cs.take_watches(literal);
+        cs.restore_watches(literal, watches.clone());
+        watches
+    }
 
     #[test]
     fn test_iter_clause_store() {
@@ -366,9 +673,9 @@ mod test {
 
         // Ensure we get coverage of long, pair, and unit clauses
         let clauses = vec![
-            Clause::new(&vec![a, b, c]),
-            Clause::new(&vec![b, c]),
-            Clause::new(&vec![c]),
+            Clause::new(&[a, b, c]),
+            Clause::new(&[b, c]),
+            Clause::new(&[c]),
         ];
 
         let cs = ClauseStore::new(clauses);
@@ -380,4 +687,74 @@ mod test {
         assert_eq!(clauses[1].literals(&cs).collect_vec(), vec![b, c]);
         assert_eq!(clauses[2].literals(&cs).collect_vec(), vec![c]);
     }
+
+    #[test]
+    fn test_update_watch() {
+        let a = Literal::new(Variable(0), true);
+        let b = Literal::new(Variable(1), true);
+        let c = Literal::new(Variable(2), true);
+
+        let mut cs = ClauseStore::new(vec![Clause::new(&[a, b, c])]);
+        let clause = cs.iter().next().unwrap();
+        // The first two literals are watched
+        assert_eq!(watches_on(&mut cs, a).len(), 1);
+        assert_eq!(watches_on(&mut cs, b).len(), 1);
+        assert_eq!(watches_on(&mut cs, c).len(), 0);
+
+        // With a=false, the watch moves onto c
+        let mut assignment = LiteralSet::from_assignment_vec(&vec![a.invert()]);
+        let watches = cs.take_watches(a);
+        assert_eq!(cs.update_watch(clause, a, &assignment), WatchUpdate::Moved);
+        cs.restore_watches(a, watches);
+        assert_eq!(watches_on(&mut cs, c).len(), 1);
+        assert_eq!(clause.literals(&cs).take(2).collect_vec(), vec![b, c]);
+
+        // With c=false too, the clause is unit on b
+        assignment.add(c.invert());
+        assert_eq!(
+            cs.update_watch(clause, c, &assignment),
+            WatchUpdate::Unit(b)
+        );
+
+        // And if b was true, it is satisfied
+        assignment.add(b);
+        assert_eq!(
+            cs.update_watch(clause, c, &assignment),
+            WatchUpdate::Satisfied(b)
+        );
+        assert!(matches!(clause, ClauseRef::Long { .. }));
+    }
+
+    #[test]
+    fn test_delete_learnt_clauses() {
+        let lits = (0..5)
+            .map(|i| Literal::new(Variable(i), true))
+            .collect_vec();
+
+        let mut cs = ClauseStore::new(vec![Clause::new(&lits[0..3])]);
+        let first = cs.add_learnt_clause(vec![lits[3], lits[1], lits[0]], 3, &[]);
+        let second = cs.add_learnt_clause(vec![lits[4], lits[2], lits[1]], 2, &[]);
+        assert_eq!(cs.learnt_clause_count(), 2);
+
+        let moved = cs.delete_clauses(&[first].into_iter().collect());
+        assert_eq!(cs.learnt_clause_count(), 1);
+        assert_eq!(cs.iter().count(), 2);
+
+        // The surviving learnt clause moves into the space left behind, in the same order
+        let second = moved[&second];
+        assert_eq!(
+            second.literals(&cs).collect_vec(),
+            vec![lits[4], lits[2], lits[1]]
+        );
+        assert_eq!(cs.learnt_clauses().next().unwrap().1.lbd, 2);
+        // And is still watched on its first two literals
+        assert_eq!(watches_on(&mut cs, lits[3]).len(), 0);
+        assert_eq!(
+            watches_on(&mut cs, lits[4]),
+            vec![Watch {
+                clause: second,
+                blocker: lits[2]
+            }]
+        );
+    }
 }
@@ -0,0 +1,165 @@
+use std::sync::Arc;
+
+use crate::variable_registry::VariableRegister;
+
+use super::backtrack::{BacktrackStrategy, Backtracking};
+use super::decision::{DecisionStrategy, Decisions};
+use super::reduce::{ClauseDeletion, ClauseDeletionPolicy};
+use super::restart::{RestartPolicy, Restarts};
+
+/// One of the solver's strategies: either one of ours, or one of the user's own. Each search
+/// builds its own from this, so that a cloned instance starts out with the strategy as it was
+/// configured, rather than as far as the original has got with it.
+pub(crate) enum Strategy<B, T: ?Sized> {
+    BuiltIn(B),
+    Custom(Box<dyn Prototype<T>>),
+}
+
+impl<B: Clone, T: ?Sized> Clone for Strategy<B, T> {
+    fn clone(&self) -> Self {
+        match self {
+            Strategy::BuiltIn(built_in) => Strategy::BuiltIn(built_in.clone()),
+            Strategy::Custom(prototype) => Strategy::Custom(prototype.clone_prototype()),
+        }
+    }
+}
+
+/// A custom strategy, as configured, which copies of are made to search with
+pub(crate) trait Prototype<T: ?Sized>: Send {
+    fn instantiate(&self) -> Box<T>;
+
+    fn clone_prototype(&self) -> Box<dyn Prototype<T>>;
+}
+
+impl<S: DecisionStrategy + Clone + 'static> Prototype<dyn DecisionStrategy> for S {
+    fn instantiate(&self) -> Box<dyn DecisionStrategy> {
+        Box::new(self.clone())
+    }
+
+    fn clone_prototype(&self) -> Box<dyn Prototype<dyn DecisionStrategy>> {
+        Box::new(self.clone())
+    }
+}
+
+impl<S: RestartPolicy + Clone + 'static> Prototype<dyn RestartPolicy> for S {
+    fn instantiate(&self) -> Box<dyn RestartPolicy> {
+        Box::new(self.clone())
+    }
+
+    fn clone_prototype(&self) -> Box<dyn Prototype<dyn RestartPolicy>> {
+        Box::new(self.clone())
+    }
+}
+
+impl<S: ClauseDeletionPolicy + Clone + 'static> Prototype<dyn ClauseDeletionPolicy> for S {
+    fn instantiate(&self) -> Box<dyn ClauseDeletionPolicy> {
+        Box::new(self.clone())
+    }
+
+    fn clone_prototype(&self) -> Box<dyn Prototype<dyn ClauseDeletionPolicy>> {
+        Box::new(self.clone())
+    }
+}
+
+impl Strategy<Decisions, dyn DecisionStrategy> {
+    /// A strategy that knows about every variable registered
+    pub(crate) fn build(&self, variables: &VariableRegister) -> Box<dyn DecisionStrategy> {
+        match self {
+            Strategy::BuiltIn(decisions) => decisions.strategy(variables),
+            Strategy::Custom(prototype) => {
+                let mut strategy = prototype.instantiate();
+                for &var in variables.iter() {
+                    strategy.add_variable(var);
+                }
+                strategy
+            }
+        }
+    }
+}
+
+impl Strategy<Restarts, dyn RestartPolicy> {
+    pub(crate) fn build(&self) -> Box<dyn RestartPolicy> {
+        match self {
+            Strategy::BuiltIn(restarts) => restarts.policy(),
+            Strategy::Custom(prototype) => prototype.instantiate(),
+        }
+    }
+}
+
+impl Strategy<ClauseDeletion, dyn ClauseDeletionPolicy> {
+    pub(crate) fn build(&self) -> Box<dyn ClauseDeletionPolicy> {
+        match self {
+            Strategy::BuiltIn(clause_deletion) => clause_deletion.policy(),
+            Strategy::Custom(prototype) => prototype.instantiate(),
+        }
+    }
+}
+
+/// The strategies the solver searches with, for `Instance::solve_with`. Each can be one we
+/// provide, or your own implementation of its trait. Those left unset stay as they were.
+///
+/// Your own strategies carry over to clones of the instance, and so to `solve_portfolio` and
+/// `count_models`. A backtrack strategy is shared between them, as it is only ever borrowed.
+/// The rest have to be `Clone`: each search starts with its own copy, as you configured it.
+#[derive(Default)]
+pub struct SolverConfig {
+    pub(crate) backtracking: Option<Arc<dyn BacktrackStrategy>>,
+    pub(crate) decisions: Option<Strategy<Decisions, dyn DecisionStrategy>>,
+    pub(crate) restarts: Option<Strategy<Restarts, dyn RestartPolicy>>,
+    pub(crate) clause_deletion: Option<Strategy<ClauseDeletion, dyn ClauseDeletionPolicy>>,
+}
+
+impl SolverConfig {
+    pub fn new() -> SolverConfig {
+        SolverConfig::default()
+    }
+
+    pub fn backtracking(mut self, backtracking: Backtracking) -> SolverConfig {
+        self.backtracking = Some(backtracking.strategy());
+        self
+    }
+
+    pub fn backtrack_strategy(
+        mut self,
+        strategy: impl BacktrackStrategy + 'static,
+    ) -> SolverConfig {
+        self.backtracking = Some(Arc::new(strategy));
+        self
+    }
+
+    pub fn decisions(mut self, decisions: Decisions) -> SolverConfig {
+        self.decisions = Some(Strategy::BuiltIn(decisions));
+        self
+    }
+
+    pub fn decision_strategy(
+        mut self,
+        strategy: impl DecisionStrategy + Clone + 'static,
+    ) -> SolverConfig {
+        self.decisions = Some(Strategy::Custom(Box::new(strategy)));
+        self
+    }
+
+    pub fn restarts(mut self, restarts: Restarts) -> SolverConfig {
+        self.restarts = Some(Strategy::BuiltIn(restarts));
+        self
+    }
+
+    pub fn restart_policy(mut self, policy: impl RestartPolicy + Clone + 'static) -> SolverConfig {
+        self.restarts = Some(Strategy::Custom(Box::new(policy)));
+        self
+    }
+
+    pub fn clause_deletion(mut self, clause_deletion: ClauseDeletion) -> SolverConfig {
+        self.clause_deletion = Some(Strategy::BuiltIn(clause_deletion));
+        self
+    }
+
+    pub fn clause_deletion_policy(
+        mut self,
+        policy: impl ClauseDeletionPolicy + Clone + 'static,
+    ) -> SolverConfig {
+        self.clause_deletion = Some(Strategy::Custom(Box::new(policy)));
+        self
+    }
+}
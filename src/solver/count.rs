@@ -0,0 +1,430 @@
+use fnv::FnvHashMap;
+use num_bigint::BigUint;
+
+use crate::instance::*;
+
+use super::dfs::{Instance, SolveResult};
+use super::limits::Limits;
+use super::model::Model;
+use super::verify::ModelError;
+
+/// Counts models by branching on the projected variables, as in sharpSAT. After each branch,
+/// the clauses left unsatisfied split into components that share no variables, whose counts
+/// multiply. Components recur across branches, so their counts are cached by their clauses.
+///
+/// Before splitting, the CDCL solver checks that the current assignment extends to a model,
+/// assuming it incrementally so that what it learns carries over. Unsatisfiable branches are
+/// cut off straight away, and every component we split off can be satisfied: one without
+/// projected variables counts once.
+pub(crate) struct ModelCounter {
+    oracle: Instance,
+    // The last model the oracle found
+    witness: Option<Model>,
+    clauses: Vec<Vec<Literal>>,
+    // The clauses each variable appears in
+    occurrences: Vec<Vec<usize>>,
+    projected: Vec<bool>,
+    values: Vec<Option<bool>>,
+    trail: Vec<Variable>,
+    cache: FnvHashMap<Vec<Vec<Literal>>, BigUint>,
+    // Marks for the clauses and variables seen while finding components
+    clause_seen: Vec<bool>,
+    variable_seen: Vec<bool>,
+}
+
+impl ModelCounter {
+    pub(crate) fn new(
+        instance: &Instance,
+        projection: impl Iterator<Item = Variable>,
+    ) -> ModelCounter {
+        // A clone starts from scratch, and the counter needs every answer
+        let mut oracle = instance.clone();
+        oracle.set_limits(Limits::default());
+        // Every solve assumes the trail, which would put back whatever preprocessing removed
+        oracle.set_preprocessing(false);
+        let variable_count = instance.variables.count();
+        let clauses = instance
+            .clauses
+            .iter()
+            .map(|clause| clause.literals().clone())
+            .collect::<Vec<_>>();
+        let mut occurrences = vec![vec![]; variable_count];
+        for (ix, clause) in clauses.iter().enumerate() {
+            for lit in clause {
+                occurrences[lit.var().idx()].push(ix);
+            }
+        }
+        let mut projected = vec![false; variable_count];
+        for var in projection {
+            projected[var.idx()] = true;
+        }
+        ModelCounter {
+            oracle,
+            witness: None,
+            clause_seen: vec![false; clauses.len()],
+            clauses,
+            occurrences,
+            projected,
+            values: vec![None; variable_count],
+            trail: vec![],
+            cache: FnvHashMap::default(),
+            variable_seen: vec![false; variable_count],
+        }
+    }
+
+    /// Fails if the oracle finds a model that does not satisfy the instance, which model
+    /// verification catches
+    pub(crate) fn count(mut self) -> Result<BigUint, ModelError> {
+        let units = self
+            .clauses
+            .iter()
+            .filter_map(|clause| match clause.as_slice() {
+                [] => Some(None),
+                [lit] => Some(Some(*lit)),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        for unit in units {
+            match unit {
+                Some(lit) if self.assign(lit) => {}
+                _ => return Ok(BigUint::from(0u32)),
+            }
+        }
+        let variables = (0..self.values.len() as u64)
+            .map(Variable)
+            .collect::<Vec<_>>();
+        let clauses = (0..self.clauses.len()).collect::<Vec<_>>();
+        self.count_clauses(&clauses, &variables)
+    }
+
+    /// Counts the assignments to the unassigned projected variables, out of those given, that
+    /// extend to satisfy the clauses. The variables must include every unassigned variable of
+    /// the unsatisfied clauses.
+    fn count_clauses(
+        &mut self,
+        clauses: &[usize],
+        variables: &[Variable],
+    ) -> Result<BigUint, ModelError> {
+        if !self.satisfiable()? {
+            return Ok(BigUint::from(0u32));
+        }
+        let components = self.components(clauses);
+
+        // Projected variables left in no clause can take either value
+        for (_, component_variables) in components.iter() {
+            for var in component_variables {
+                self.variable_seen[var.idx()] = true;
+            }
+        }
+        let free = variables
+            .iter()
+            .filter(|var| {
+                self.projected[var.idx()]
+                    && self.values[var.idx()].is_none()
+                    && !self.variable_seen[var.idx()]
+            })
+            .count();
+        for (_, component_variables) in components.iter() {
+            for var in component_variables {
+                self.variable_seen[var.idx()] = false;
+            }
+        }
+
+        let mut count = BigUint::from(1u32) << free;
+        for (component_clauses, component_variables) in components {
+            count *= self.count_component(&component_clauses, &component_variables)?;
+        }
+        Ok(count)
+    }
+
+    fn count_component(
+        &mut self,
+        clauses: &[usize],
+        variables: &[Variable],
+    ) -> Result<BigUint, ModelError> {
+        let key = self.component_key(clauses);
+        if let Some(count) = self.cache.get(&key) {
+            return Ok(count.clone());
+        }
+
+        let count = match self.branching_variable(clauses, variables) {
+            None => BigUint::from(1u32),
+            Some(var) => {
+                let mut count = BigUint::from(0u32);
+                for polarity in [true, false] {
+                    let mark = self.trail.len();
+                    if self.assign(Literal::new(var, polarity)) {
+                        count += self.count_clauses(clauses, variables)?;
+                    }
+                    self.undo(mark);
+                }
+                count
+            }
+        };
+        self.cache.insert(key, count.clone());
+        Ok(count)
+    }
+
+    /// The unsatisfied clauses, grouped by the unassigned variables they share
+    fn components(&mut self, clauses: &[usize]) -> Vec<(Vec<usize>, Vec<Variable>)> {
+        let mut components = vec![];
+        for &start in clauses {
+            if self.clause_seen[start] || self.satisfied(start) {
+                continue;
+            }
+            self.clause_seen[start] = true;
+            let mut component_clauses = vec![start];
+            let mut component_variables = vec![];
+            let mut next = 0;
+            while next < component_clauses.len() {
+                let clause = component_clauses[next];
+                next += 1;
+                for ix in 0..self.clauses[clause].len() {
+                    let var = self.clauses[clause][ix].var();
+                    if self.values[var.idx()].is_some() || self.variable_seen[var.idx()] {
+                        continue;
+                    }
+                    self.variable_seen[var.idx()] = true;
+                    component_variables.push(var);
+                    for &other in self.occurrences[var.idx()].iter() {
+                        if !self.clause_seen[other] && !self.satisfied(other) {
+                            self.clause_seen[other] = true;
+                            component_clauses.push(other);
+                        }
+                    }
+                }
+            }
+            components.push((component_clauses, component_variables));
+        }
+
+        for (component_clauses, component_variables) in components.iter() {
+            for &clause in component_clauses {
+                self.clause_seen[clause] = false;
+            }
+            for var in component_variables {
+                self.variable_seen[var.idx()] = false;
+            }
+        }
+        components
+    }
+
+    /// The clauses of the component, without their false literals. Components with the same
+    /// key have the same count
+    fn component_key(&self, clauses: &[usize]) -> Vec<Vec<Literal>> {
+        let mut key = clauses
+            .iter()
+            .map(|&clause| {
+                self.clauses[clause]
+                    .iter()
+                    .copied()
+                    .filter(|lit| self.values[lit.var().idx()].is_none())
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        key.sort();
+        key
+    }
+
+    /// The projected variable of the component that appears in the most clauses
+    fn branching_variable(&self, clauses: &[usize], variables: &[Variable]) -> Option<Variable> {
+        let mut occurrences = FnvHashMap::default();
+        for &clause in clauses {
+            for lit in self.clauses[clause].iter() {
+                *occurrences.entry(lit.var()).or_insert(0) += 1;
+            }
+        }
+        variables
+            .iter()
+            .copied()
+            .filter(|var| self.projected[var.idx()])
+            .max_by_key(|var| (occurrences.get(var).copied().unwrap_or(0), var.0))
+    }
+
+    /// Assigns the literal and propagates it, returning false on conflict
+    fn assign(&mut self, lit: Literal) -> bool {
+        let mut queue = vec![lit];
+        while let Some(lit) = queue.pop() {
+            match self.values[lit.var().idx()] {
+                Some(value) if value == lit.polarity() => continue,
+                Some(_) => return false,
+                None => {}
+            }
+            self.values[lit.var().idx()] = Some(lit.polarity());
+            self.trail.push(lit.var());
+
+            for ix in 0..self.occurrences[lit.var().idx()].len() {
+                let clause = self.occurrences[lit.var().idx()][ix];
+                if self.satisfied(clause) {
+                    continue;
+                }
+                let mut unassigned = self.clauses[clause]
+                    .iter()
+                    .filter(|l| self.values[l.var().idx()].is_none());
+                match (unassigned.next(), unassigned.next()) {
+                    (None, _) => return false,
+                    (Some(&unit), None) => queue.push(unit),
+                    _ => {}
+                }
+            }
+        }
+        true
+    }
+
+    fn undo(&mut self, mark: usize) {
+        for var in self.trail.drain(mark..) {
+            self.values[var.idx()] = None;
+        }
+    }
+
+    /// Whether the current assignment extends to a model
+    fn satisfiable(&mut self) -> Result<bool, ModelError> {
+        // The last model found often still fits, as propagation only assigns the literals
+        // every model extending the assignment agrees on
+        if let Some(witness) = &self.witness {
+            if self
+                .trail
+                .iter()
+                .all(|&var| witness.value(var) == self.values[var.idx()])
+            {
+                return Ok(true);
+            }
+        }
+        let assumptions = self
+            .trail
+            .iter()
+            .map(|&var| Literal::new(var, self.values[var.idx()].unwrap()))
+            .collect::<Vec<_>>();
+        match self.oracle.solve_with_assumptions(&assumptions).result {
+            SolveResult::Sat(model) => {
+                self.witness = Some(model);
+                Ok(true)
+            }
+            SolveResult::Unsat => Ok(false),
+            SolveResult::Unknown(limit) => unreachable!("counting has no limits, hit {:?}", limit),
+            SolveResult::InvalidModel { error, .. } => Err(error),
+        }
+    }
+
+    fn satisfied(&self, clause: usize) -> bool {
+        self.clauses[clause]
+            .iter()
+            .any(|lit| self.values[lit.var().idx()] == Some(lit.polarity()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use num_bigint::BigUint;
+
+    use crate::{
+        dimacs,
+        instance::*,
+        solver::{rng::Rng, Instance},
+        variable_registry::VariableRegister,
+    };
+
+    fn instance(clauses: Vec<Clause>, variable_count: usize) -> Instance {
+        let mut variables = VariableRegister::new();
+        for ix in 0..variable_count {
+            variables.create_original(&ix.to_string());
+        }
+        Instance::new_from_clauses(clauses, variables)
+    }
+
+    fn brute_force_count(
+        clauses: &[Clause],
+        variable_count: usize,
+        projection: &[Variable],
+    ) -> u64 {
+        let mut projected_models = vec![];
+        for bits in 0..(1u64 << variable_count) {
+            let value = |var: Variable| bits & (1 << var.0) != 0;
+            let satisfied = clauses.iter().all(|clause| {
+                clause
+                    .literals()
+                    .iter()
+                    .any(|lit| value(lit.var()) == lit.polarity())
+            });
+            if satisfied {
+                projected_models.push(projection.iter().map(|&var| value(var)).collect::<Vec<_>>());
+            }
+        }
+        projected_models.sort();
+        projected_models.dedup();
+        projected_models.len() as u64
+    }
+
+    #[test]
+    fn test_count_random_formulas() {
+        let mut rng = Rng::new(15);
+        for _ in 0..200 {
+            let variable_count = 3 + (rng.next_u64() % 8) as usize;
+            let clause_count = rng.next_u64() % 20;
+            let clauses = (0..clause_count)
+                .map(|ix| {
+                    let mut literals = (0..1 + rng.next_u64() % 3)
+                        .map(|_| {
+                            let var = Variable(rng.next_u64() % variable_count as u64);
+                            Literal::new(var, rng.next_bool())
+                        })
+                        .collect::<Vec<_>>();
+                    literals.sort();
+                    literals.dedup_by_key(|lit| lit.var());
+                    Clause::new_with_id(ix as usize, &literals)
+                })
+                .collect::<Vec<_>>();
+            let all = (0..variable_count as u64).map(Variable).collect::<Vec<_>>();
+            let projection = all
+                .iter()
+                .copied()
+                .filter(|_| rng.next_bool())
+                .collect::<Vec<_>>();
+
+            for projection in [&all, &projection] {
+                let instance = instance(clauses.clone(), variable_count);
+                assert_eq!(
+                    instance
+                        .count_projected_models(projection.iter().copied())
+                        .unwrap(),
+                    BigUint::from(brute_force_count(&clauses, variable_count, projection)),
+                    "{:?} projected onto {:?}",
+                    clauses,
+                    projection
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_count_dimacs() {
+        let mut instance =
+            dimacs::parse("examples/problem_specs/sat/aim-50-1_6-yes1-4.cnf").unwrap();
+        let expected = instance.enumerate().count();
+        assert_eq!(instance.count_models().unwrap(), BigUint::from(expected));
+
+        let instance = dimacs::parse("examples/problem_specs/unsat/dubois20.cnf").unwrap();
+        assert_eq!(instance.count_models().unwrap(), BigUint::from(0u32));
+    }
+
+    #[test]
+    fn test_count_beyond_u64() {
+        // Two hundred variables, with each pair forced to differ
+        let clauses = (0..100u64)
+            .flat_map(|ix| {
+                let (a, b) = (Variable(2 * ix), Variable(2 * ix + 1));
+                [
+                    Clause::new_with_id(
+                        2 * ix as usize,
+                        &[Literal::new(a, true), Literal::new(b, true)],
+                    ),
+                    Clause::new_with_id(
+                        2 * ix as usize + 1,
+                        &[Literal::new(a, false), Literal::new(b, false)],
+                    ),
+                ]
+            })
+            .collect::<Vec<_>>();
+        let instance = instance(clauses, 200);
+        assert_eq!(instance.count_models().unwrap(), BigUint::from(1u32) << 100);
+    }
+}
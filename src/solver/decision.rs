@@ -0,0 +1,325 @@
+use std::collections::BTreeSet;
+
+use crate::instance::*;
+use crate::variable_registry::VariableRegister;
+
+use super::trail::Trail;
+
+/// Which variable the solver branches on next
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Decisions {
+    /// The unassigned variable that has taken part in the most conflicts lately (VSIDS)
+    #[default]
+    Vsids,
+    /// The first unassigned variable, in the order the variables were created
+    InOrder,
+}
+
+impl Decisions {
+    pub(crate) fn strategy(&self, variables: &VariableRegister) -> Box<dyn DecisionStrategy> {
+        match *self {
+            Decisions::Vsids => Box::new(VsidsStrategy::new(variables)),
+            Decisions::InOrder => Box::new(TraversalPath::new(variables)),
+        }
+    }
+}
+
+/// Picks the variables the solver branches on. Implement this to plug your own heuristic in
+/// with `SolverConfig::decision_strategy`. The solver tells it about every variable of the
+/// problem with `add_variable` before asking it for any.
+pub trait DecisionStrategy: Send {
+    /// Picks the next variable to branch on. None once every variable has been assigned. A
+    /// variable handed out should not be handed out again until `unassigned` gives it back:
+    /// the solver passes over those that are assigned already, or that preprocessing
+    /// eliminated, and eliminated variables only come back that way
+    fn next(&mut self, trail: &Trail) -> Option<Variable>;
+
+    /// Called with every variable that took part in deriving a learnt clause
+    fn bump(&mut self, _variables: &[Variable]) {}
+
+    /// Called once per conflict, after the variables involved have been bumped
+    fn decay(&mut self) {}
+
+    /// Called with the assignments undone by a backtrack
+    fn unassigned(&mut self, _literals: &[Literal]) {}
+
+    /// Called when a variable is added to the problem after the strategy was created
+    fn add_variable(&mut self, var: Variable);
+}
+
+/// Branches on the variables in the order they were registered
+#[derive(Debug, Clone)]
+pub(crate) struct TraversalPath {
+    // The variables not handed out since they were last unassigned
+    variables: BTreeSet<Variable>,
+}
+
+impl TraversalPath {
+    pub(crate) fn new(variables: &VariableRegister) -> TraversalPath {
+        TraversalPath {
+            variables: variables.iter().copied().collect(),
+        }
+    }
+}
+
+impl DecisionStrategy for TraversalPath {
+    fn next(&mut self, trail: &Trail) -> Option<Variable> {
+        while let Some(var) = self.variables.pop_first() {
+            if trail.value(var).is_none() {
+                return Some(var);
+            }
+        }
+        None
+    }
+
+    fn unassigned(&mut self, literals: &[Literal]) {
+        self.variables.extend(literals.iter().map(|lit| lit.var()));
+    }
+
+    fn add_variable(&mut self, var: Variable) {
+        self.variables.insert(var);
+    }
+}
+
+const VSIDS_DECAY: f64 = 0.95;
+const VSIDS_RESCALE_LIMIT: f64 = 1e100;
+
+/// Exponential VSIDS. Each conflict bumps the activity of the variables involved in it, and the
+/// bump grows geometrically so older bumps decay relative to newer ones. We branch on the
+/// unassigned variable with the highest activity.
+#[derive(Debug, Clone)]
+pub(crate) struct VsidsStrategy {
+    activity: Vec<f64>,
+    increment: f64,
+    heap: ActivityHeap,
+}
+
+impl VsidsStrategy {
+    pub(crate) fn new(variables: &VariableRegister) -> VsidsStrategy {
+        let activity = vec![0.0; variables.count()];
+        let mut heap = ActivityHeap::new(variables.count());
+        for &var in variables.iter() {
+            heap.insert(var, &activity);
+        }
+        VsidsStrategy {
+            activity,
+            increment: 1.0,
+            heap,
+        }
+    }
+
+    #[allow(dead_code)]
+    pub(crate) fn activity(&self, var: Variable) -> f64 {
+        self.activity[var.idx()]
+    }
+
+    fn rescale(&mut self) {
+        for activity in self.activity.iter_mut() {
+            *activity /= VSIDS_RESCALE_LIMIT;
+        }
+        self.increment /= VSIDS_RESCALE_LIMIT;
+    }
+}
+
+impl DecisionStrategy for VsidsStrategy {
+    fn next(&mut self, trail: &Trail) -> Option<Variable> {
+        // Assigned variables are lazily dropped from the heap, and put back on backtrack
+        while let Some(var) = self.heap.pop(&self.activity) {
+            if trail.assignment().get(var).is_none() {
+                return Some(var);
+            }
+        }
+        None
+    }
+
+    fn bump(&mut self, variables: &[Variable]) {
+        for &var in variables {
+            self.activity[var.idx()] += self.increment;
+            if self.activity[var.idx()] > VSIDS_RESCALE_LIMIT {
+                self.rescale();
+            }
+            self.heap.increased(var, &self.activity);
+        }
+    }
+
+    fn decay(&mut self) {
+        self.increment /= VSIDS_DECAY;
+        if self.increment > VSIDS_RESCALE_LIMIT {
+            self.rescale();
+        }
+    }
+
+    fn unassigned(&mut self, literals: &[Literal]) {
+        for lit in literals {
+            self.heap.insert(lit.var(), &self.activity);
+        }
+    }
+
+    fn add_variable(&mut self, var: Variable) {
+        assert_eq!(var.idx(), self.activity.len());
+        self.activity.push(0.0);
+        self.heap.positions.push(None);
+        self.heap.insert(var, &self.activity);
+    }
+}
+
+/// A binary max-heap of variables keyed by activity, which tracks the position of each variable
+/// so that its key can be increased in place. Ties go to the lowest variable.
+#[derive(Debug, Clone)]
+struct ActivityHeap {
+    heap: Vec<Variable>,
+    // Indexed by Variable::idx
+    positions: Vec<Option<usize>>,
+}
+
+impl ActivityHeap {
+    fn new(variable_count: usize) -> ActivityHeap {
+        ActivityHeap {
+            heap: Vec::with_capacity(variable_count),
+            positions: vec![None; variable_count],
+        }
+    }
+
+    fn contains(&self, var: Variable) -> bool {
+        self.positions[var.idx()].is_some()
+    }
+
+    fn insert(&mut self, var: Variable, activity: &[f64]) {
+        if self.contains(var) {
+            return;
+        }
+        self.heap.push(var);
+        self.positions[var.idx()] = Some(self.heap.len() - 1);
+        self.sift_up(self.heap.len() - 1, activity);
+    }
+
+    fn pop(&mut self, activity: &[f64]) -> Option<Variable> {
+        let top = *self.heap.first()?;
+        let last = self.heap.pop().unwrap();
+        self.positions[top.idx()] = None;
+        if !self.heap.is_empty() {
+            self.heap[0] = last;
+            self.positions[last.idx()] = Some(0);
+            self.sift_down(0, activity);
+        }
+        Some(top)
+    }
+
+    /// Restores the heap property after the activity of the variable has increased
+    fn increased(&mut self, var: Variable, activity: &[f64]) {
+        if let Some(pos) = self.positions[var.idx()] {
+            self.sift_up(pos, activity);
+        }
+    }
+
+    fn before(a: Variable, b: Variable, activity: &[f64]) -> bool {
+        let (x, y) = (activity[a.idx()], activity[b.idx()]);
+        x > y || (x == y && a < b)
+    }
+
+    fn sift_up(&mut self, mut pos: usize, activity: &[f64]) {
+        let var = self.heap[pos];
+        while pos > 0 {
+            let parent = (pos - 1) / 2;
+            if !Self::before(var, self.heap[parent], activity) {
+                break;
+            }
+            self.heap[pos] = self.heap[parent];
+            self.positions[self.heap[pos].idx()] = Some(pos);
+            pos = parent;
+        }
+        self.heap[pos] = var;
+        self.positions[var.idx()] = Some(pos);
+    }
+
+    fn sift_down(&mut self, mut pos: usize, activity: &[f64]) {
+        let var = self.heap[pos];
+        loop {
+            let left = 2 * pos + 1;
+            if left >= self.heap.len() {
+                break;
+            }
+            let right = left + 1;
+            let child = if right < self.heap.len()
+                && Self::before(self.heap[right], self.heap[left], activity)
+            {
+                right
+            } else {
+                left
+            };
+            if !Self::before(self.heap[child], var, activity) {
+                break;
+            }
+            self.heap[pos] = self.heap[child];
+            self.positions[self.heap[pos].idx()] = Some(pos);
+            pos = child;
+        }
+        self.heap[pos] = var;
+        self.positions[var.idx()] = Some(pos);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{instance::*, solver::trail::Trail, variable_registry::VariableRegister};
+
+    use super::{DecisionStrategy, TraversalPath, VsidsStrategy};
+
+    fn register(count: usize) -> VariableRegister {
+        let mut vr = VariableRegister::new();
+        for ix in 0..count {
+            vr.create_original(&ix.to_string());
+        }
+        vr
+    }
+
+    #[test]
+    fn test_traversal_path_in_order() {
+        let vr = register(3);
+        let mut trail = Trail::new();
+        let mut strategy = TraversalPath::new(&vr);
+
+        assert_eq!(strategy.next(&trail), Some(Variable(0)));
+        trail.add_decision(Literal::new(Variable(0), true));
+        assert_eq!(strategy.next(&trail), Some(Variable(1)));
+    }
+
+    #[test]
+    fn test_vsids_prefers_bumped_variables() {
+        let vr = register(4);
+        let mut trail = Trail::new();
+        let mut strategy = VsidsStrategy::new(&vr);
+
+        // With no activity, we fall back to registration order
+        assert_eq!(strategy.next(&trail), Some(Variable(0)));
+        strategy.unassigned(&[Literal::new(Variable(0), true)]);
+
+        strategy.bump(&[Variable(2), Variable(3)]);
+        strategy.decay();
+        strategy.bump(&[Variable(3)]);
+        assert!(strategy.activity(Variable(3)) > strategy.activity(Variable(2)));
+
+        assert_eq!(strategy.next(&trail), Some(Variable(3)));
+        trail.add_decision(Literal::new(Variable(3), true));
+        assert_eq!(strategy.next(&trail), Some(Variable(2)));
+        trail.add_decision(Literal::new(Variable(2), false));
+
+        // Backtracking puts the variables back into contention
+        let backtracked = trail.backtrack(0);
+        strategy.unassigned(&backtracked.assignments);
+        assert_eq!(strategy.next(&trail), Some(Variable(3)));
+    }
+
+    #[test]
+    fn test_vsids_skips_assigned_variables() {
+        let vr = register(3);
+        let mut trail = Trail::new();
+        let mut strategy = VsidsStrategy::new(&vr);
+
+        trail.add_inferred(Literal::new(Variable(0), true));
+        trail.add_inferred(Literal::new(Variable(1), false));
+        assert_eq!(strategy.next(&trail), Some(Variable(2)));
+        trail.add_decision(Literal::new(Variable(2), true));
+        assert_eq!(strategy.next(&trail), None);
+    }
+}
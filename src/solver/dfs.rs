@@ -1,39 +1,90 @@
 use std::fmt;
-use std::rc::Rc;
+use std::io::{self, Write};
+use std::sync::Arc;
 
-use log::{info, trace};
+use fnv::FnvHashSet;
+use itertools::Itertools;
+use log::{info, log_enabled, trace, Level};
+use num_bigint::BigUint;
 
 use crate::instance::*;
-use crate::solver::backtrack::{BacktrackStrategy, ConflictAnalyzer};
+use crate::solver::backtrack::{BacktrackStrategy, Backtracking, ConflictAnalyzer};
+use crate::solver::config::{SolverConfig, Strategy};
+use crate::solver::decision::{DecisionStrategy, Decisions};
 use crate::solver::knowledge_graph::KnowledgeGraph;
+use crate::solver::limits::{Budget, Limit, Limits};
+use crate::solver::phase::{InitialPhase, SavedPhases};
+use crate::solver::reduce::{ClauseDeletion, ClauseDeletionPolicy, LearntClause};
+use crate::solver::restart::{RestartPolicy, Restarts};
 use crate::solver::sorted_vec::sort_and_dedupe;
 use crate::solver::trail::Trail;
 use crate::solver::unit_propagator::{find_inital_assignment, InitialAssignmentResult};
 use crate::variable_registry::VariableRegister;
 
 use super::assignment_set::LiteralSet;
-use super::backtrack::{AnalyzedConflict, BackjumpStrategy, Conflict};
-use super::clause_store::ClauseStore;
+use super::backtrack::{AnalyzedConflict, Conflict};
+use super::clause_store::{ClauseRef, ClauseRefResolver, ClauseStore};
+use super::count::ModelCounter;
+use super::enumerate::Enumerate;
+use super::model::Model;
+use super::portfolio::{ClauseSharing, Portfolio};
+use super::preprocess::{Preprocessor, Reconstruction};
+use super::probe::Prober;
+use super::proof::{Proof, ProofFormat};
+use super::root::hints;
+use super::subsume::subsume_learnt_clauses;
 use super::unit_propagator::{record_initial_assignment, UnitPropagator};
+use super::verify::{verify_model, ModelError};
+use super::vivify::Vivifier;
 
-#[derive(Debug, Clone)]
-struct TraversalPath {
-    variables: Rc<VariableRegister>,
-}
-
-impl TraversalPath {
-    fn next(&self, path: &Trail) -> Option<&Variable> {
-        self.variables
-            .iter()
-            .find(|&&l| path.assignment().get(l).is_none())
-    }
-}
+// How many conflicts go by between subsumption passes over the learnt clauses
+const SUBSUMPTION_INTERVAL: usize = 5000;
+// How many conflicts go by between rounds of probing, and how many propagations each may make
+const PROBING_INTERVAL: usize = 10000;
+const PROBING_BUDGET: usize = 200_000;
+// How many conflicts go by between rounds of vivification, and how many propagations each may
+// make
+const VIVIFICATION_INTERVAL: usize = 2000;
+const VIVIFICATION_BUDGET: usize = 100_000;
 
-#[derive(Clone)]
 pub struct Instance {
-    pub(crate) variables: Rc<VariableRegister>,
+    pub(crate) variables: Arc<VariableRegister>,
     pub(crate) clauses: Vec<Clause>,
-    backtrack_strategy: Rc<dyn BacktrackStrategy>,
+    backtrack_strategy: Arc<dyn BacktrackStrategy>,
+    decisions: Strategy<Decisions, dyn DecisionStrategy>,
+    initial_phase: InitialPhase,
+    restarts: Strategy<Restarts, dyn RestartPolicy>,
+    clause_deletion: Strategy<ClauseDeletion, dyn ClauseDeletionPolicy>,
+    verify_models: bool,
+    preprocessing: bool,
+    inprocessing: bool,
+    limits: Limits,
+    // Kept between calls to solve, so that the clauses learnt persist. Created on first use
+    search: Option<Search>,
+    // Handed to the search when it is created
+    proof: Option<Proof>,
+}
+
+/// Clones start their search from scratch, without the clauses the original has learnt or its
+/// proof. Their strategies start from scratch too, as they were configured
+impl Clone for Instance {
+    fn clone(&self) -> Self {
+        Instance {
+            variables: self.variables.clone(),
+            clauses: self.clauses.clone(),
+            backtrack_strategy: self.backtrack_strategy.clone(),
+            decisions: self.decisions.clone(),
+            initial_phase: self.initial_phase.clone(),
+            restarts: self.restarts.clone(),
+            clause_deletion: self.clause_deletion.clone(),
+            verify_models: self.verify_models,
+            preprocessing: self.preprocessing,
+            inprocessing: self.inprocessing,
+            limits: self.limits.clone(),
+            search: None,
+            proof: None,
+        }
+    }
 }
 
 impl Instance {
@@ -48,173 +99,1020 @@ impl Instance {
 
     pub(crate) fn new_from_clauses(clauses: Vec<Clause>, literals: VariableRegister) -> Instance {
         Instance {
-            variables: Rc::new(literals),
+            variables: Arc::new(literals),
             clauses,
-            backtrack_strategy: Self::backtrack_strategy(),
+            backtrack_strategy: Backtracking::default().strategy(),
+            decisions: Strategy::BuiltIn(Decisions::default()),
+            initial_phase: InitialPhase::default(),
+            restarts: Strategy::BuiltIn(Restarts::default()),
+            clause_deletion: Strategy::BuiltIn(ClauseDeletion::default()),
+            verify_models: cfg!(debug_assertions),
+            preprocessing: true,
+            inprocessing: true,
+            limits: Limits::default(),
+            search: None,
+            proof: None,
+        }
+    }
+
+    /// Sets the polarity the solver first branches with on each variable
+    pub fn set_initial_phase(&mut self, phase: InitialPhase) {
+        if let Some(search) = self.search.as_mut() {
+            search.phases = SavedPhases::new(self.variables.count(), &phase);
+        }
+        self.initial_phase = phase;
+    }
+
+    /// Sets the policy deciding when the solver abandons its current decisions and starts again
+    /// from the root, keeping what it has learnt. Short for configuring just the restarts
+    pub fn set_restarts(&mut self, restarts: Restarts) {
+        self.configure(SolverConfig::new().restarts(restarts));
+    }
+
+    /// Sets how far the solver goes back after each conflict. Short for configuring just the
+    /// backtracking
+    pub fn set_backtracking(&mut self, backtracking: Backtracking) {
+        self.configure(SolverConfig::new().backtracking(backtracking));
+    }
+
+    /// Switches to the strategies the config sets, for this solve and every later one, and
+    /// solves
+    pub fn solve_with(&mut self, config: SolverConfig) -> Solution {
+        self.configure(config);
+        self.solve()
+    }
+
+    /// Switches to the strategies the config sets, for every later solve. Strategies the
+    /// config leaves unset stay as they were, and those it sets start afresh.
+    pub fn configure(&mut self, config: SolverConfig) {
+        if let Some(backtrack_strategy) = config.backtracking {
+            self.backtrack_strategy = backtrack_strategy;
         }
+        if let Some(decisions) = config.decisions {
+            self.set_decision_strategy(decisions);
+        }
+        if let Some(restarts) = config.restarts {
+            self.set_restart_policy(restarts);
+        }
+        if let Some(clause_deletion) = config.clause_deletion {
+            self.set_clause_deletion_policy(clause_deletion);
+        }
+    }
+
+    fn set_decision_strategy(&mut self, decisions: Strategy<Decisions, dyn DecisionStrategy>) {
+        if let Some(search) = self.search.as_mut() {
+            // Back at the root, the new strategy has no assignments to hear about
+            search.rollback(0);
+            search.decisions = decisions.build(&self.variables);
+        }
+        self.decisions = decisions;
+    }
+
+    fn set_restart_policy(&mut self, restarts: Strategy<Restarts, dyn RestartPolicy>) {
+        if let Some(search) = self.search.as_mut() {
+            search.restart_policy = restarts.build();
+        }
+        self.restarts = restarts;
+    }
+
+    fn set_clause_deletion_policy(
+        &mut self,
+        clause_deletion: Strategy<ClauseDeletion, dyn ClauseDeletionPolicy>,
+    ) {
+        if let Some(search) = self.search.as_mut() {
+            search.clause_deletion = clause_deletion.build();
+        }
+        self.clause_deletion = clause_deletion;
+    }
+
+    /// Bounds the work done by each call to solve. A solve that runs out gives up with an
+    /// unknown status, rather than running until it finds an answer
+    pub fn set_limits(&mut self, limits: Limits) {
+        self.limits = limits;
+    }
+
+    /// Checks every model found against the clauses of the instance, answering with
+    /// `SolveResult::InvalidModel` if one is not satisfied. On by default in debug builds.
+    pub fn set_verify_models(&mut self, verify: bool) {
+        self.verify_models = verify;
+    }
+
+    /// Simplifies the clauses before the first solve, eliminating variables where that does
+    /// not grow the problem. On by default, but skipped when writing a proof.
+    pub fn set_preprocessing(&mut self, preprocessing: bool) {
+        self.preprocessing = preprocessing;
+    }
+
+    /// Simplifies the clauses as we search, at restarts: subsumes learnt clauses, probes for
+    /// failed literals and vivifies learnt clauses, then eliminates the pure literals that
+    /// leaves. On by default.
+    pub fn set_inprocessing(&mut self, inprocessing: bool) {
+        if let Some(search) = self.search.as_mut() {
+            search.inprocessing = inprocessing;
+        }
+        self.inprocessing = inprocessing;
+    }
+
+    /// Checks that the model satisfies every clause of the instance, including those added with
+    /// `add_clause`. Useful for models produced elsewhere, e.g. read with `dimacs::parse_model`.
+    pub fn verify_model(&self, model: &[Literal]) -> Result<(), ModelError> {
+        verify_model(&self.clauses, model)
     }
 
-    fn backtrack_strategy() -> Rc<dyn BacktrackStrategy> {
-        Rc::new(BackjumpStrategy {})
+    /// Writes a proof of unsatisfiability to the writer as we solve, which can be checked
+    /// against the clauses of the instance. Must be set before the first solve. Clauses added
+    /// with `add_clause` are not part of the proof.
+    pub fn set_proof(&mut self, writer: impl Write + Send + 'static, format: ProofFormat) {
+        assert!(self.search.is_none(), "proof must be set before solving");
+        self.proof = Some(Proof::new(Box::new(writer), format));
+    }
+
+    /// Flushes the proof, returning the first error hit while writing it
+    pub fn finish_proof(&mut self) -> io::Result<()> {
+        let proof = match self.search.as_mut() {
+            Some(search) => search.clause_store.proof_mut(),
+            None => self.proof.as_mut(),
+        };
+        match proof {
+            Some(proof) => proof.finish(),
+            None => Ok(()),
+        }
+    }
+
+    /// Creates a new variable, which can be used in clauses added from now on
+    pub fn add_variable(&mut self, name: &str) -> Variable {
+        let var = Arc::make_mut(&mut self.variables).create_original(name);
+        if let Some(search) = self.search.as_mut() {
+            search.add_variable(var, &self.initial_phase);
+        }
+        var
+    }
+
+    /// A variable for the solver's own use, which is not part of any model's original variables
+    pub(crate) fn add_tseitin_variable(&mut self) -> Variable {
+        let var = Arc::make_mut(&mut self.variables).create_tseitin();
+        if let Some(search) = self.search.as_mut() {
+            search.add_variable(var, &self.initial_phase);
+        }
+        var
+    }
+
+    /// Adds a clause to the problem. Everything learnt by previous calls to solve is kept, as
+    /// adding a clause can only rule out solutions. Panics if the clause contains a literal and
+    /// its negation.
+    pub fn add_clause(&mut self, literals: &[Literal]) {
+        for lit in literals {
+            assert!(
+                lit.var().idx() < self.variables.count(),
+                "unknown variable in clause: {:?}",
+                lit
+            );
+        }
+        let clause = Clause::new_with_id(self.clauses.len(), literals);
+        if let Some(search) = self.search.as_mut() {
+            search.add_clause(literals, false);
+        }
+        self.clauses.push(clause);
+    }
+
+    /// Adds a clause to the search alone, for clauses guarded by a selector variable that only
+    /// hold while it is assumed. The instance's own clauses, which clones start from and models
+    /// are verified against, stay as they are. Does nothing before the first solve, as then
+    /// there is nothing to guard.
+    pub(crate) fn add_search_clause(&mut self, literals: &[Literal]) {
+        if let Some(search) = self.search.as_mut() {
+            search.add_clause(literals, false);
+        }
+    }
+
+    /// Iterates over every model of the problem, adding a clause blocking each model once it
+    /// is found
+    pub fn enumerate(&mut self) -> Enumerate<'_> {
+        Enumerate::new(self)
+    }
+
+    /// Counts the models of the problem over its original variables, so that the variables
+    /// `ProblemBuilder` introduces for its own use do not multiply the count
+    pub fn count_models(&self) -> Result<BigUint, ModelError> {
+        self.count_projected_models(self.variables.iter_original())
+    }
+
+    /// Counts the distinct assignments to the variables that extend to a model of the problem.
+    /// Fails if model verification is on and catches a model that does not satisfy it.
+    pub fn count_projected_models(
+        &self,
+        projection: impl IntoIterator<Item = Variable>,
+    ) -> Result<BigUint, ModelError> {
+        ModelCounter::new(self, projection.into_iter()).count()
     }
 
     pub fn solve(&mut self) -> Solution {
-        let mut stats = EvaluationStats {
-            step_count: 0,
-            initial_unit_count: 0,
-            unit_prop_count: 0,
-            backtrack_count: 0,
-            learnt_clause_count: 0,
+        self.solve_with_assumptions(&[])
+    }
+
+    /// Solves the problem with the assumed literals forced to be true. The assumptions only
+    /// apply to this call: the clauses learnt from it hold regardless of them.
+    pub fn solve_with_assumptions(&mut self, assumptions: &[Literal]) -> Solution {
+        self.solve_sharing(assumptions, None)
+    }
+
+    /// Solves the problem with each of the portfolio's workers on its own thread, returning
+    /// the answer of whichever finishes first. The workers start from scratch, and what they
+    /// learn is not kept by this instance, nor written to its proof.
+    pub fn solve_portfolio(&self, portfolio: &Portfolio) -> Solution {
+        portfolio.solve(self)
+    }
+
+    /// Solves as a worker of a portfolio, when given the clauses shared with the other workers
+    pub(crate) fn solve_sharing(
+        &mut self,
+        assumptions: &[Literal],
+        sharing: Option<&mut ClauseSharing>,
+    ) -> Solution {
+        let backtrack_strategy = self.backtrack_strategy.clone();
+        let limits = self.limits.clone();
+        let (outcome, stats) =
+            self.search()
+                .solve(backtrack_strategy.as_ref(), assumptions, &limits, sharing);
+        let (result, failed_assumptions) = match outcome {
+            Outcome::Satisfiable(assignment) => (
+                SolveResult::Sat(Model::new(self.variables.clone(), assignment)),
+                vec![],
+            ),
+            Outcome::Unsatisfiable { failed_assumptions } => {
+                (SolveResult::Unsat, failed_assumptions)
+            }
+            Outcome::Unknown(limit) => (SolveResult::Unknown(limit), vec![]),
         };
-        let traversal_plan = TraversalPath {
-            variables: self.variables.clone(),
+        let result = match result {
+            SolveResult::Sat(model) if self.verify_models => {
+                match self.verify_model(&model.literals()) {
+                    Ok(()) => SolveResult::Sat(model),
+                    Err(error) => SolveResult::InvalidModel { model, error },
+                }
+            }
+            result => result,
         };
+        Solution {
+            result,
+            failed_assumptions,
+            stats,
+        }
+    }
+
+    fn search(&mut self) -> &mut Search {
+        if self.search.is_none() {
+            let variable_count = self.variables.count();
+            // The proof is checked against the original clauses, which preprocessing changes.
+            // Only add_clause can give us an empty clause, which nothing satisfies
+            let preprocessed = match self.preprocessing && self.proof.is_none() {
+                _ if self.clauses.iter().any(|c| c.literals().is_empty()) => None,
+                true => Preprocessor::new(&self.clauses, variable_count).run(),
+                false => Some((self.clauses.clone(), Reconstruction::new(variable_count))),
+            };
+            let (clauses, reconstruction, unsatisfiable) = match preprocessed {
+                Some((clauses, reconstruction)) => (clauses, reconstruction, false),
+                None => (vec![], Reconstruction::new(variable_count), true),
+            };
+            let mut clause_store = ClauseStore::new(clauses);
+            if let Some(proof) = self.proof.take() {
+                clause_store.set_proof(proof);
+            }
+            let mut search = Search::new(
+                clause_store,
+                variable_count,
+                self.decisions.build(&self.variables),
+                SavedPhases::new(variable_count, &self.initial_phase),
+                self.restarts.build(),
+                self.clause_deletion.build(),
+                reconstruction,
+            );
+            search.unsatisfiable |= unsatisfiable;
+            search.inprocessing = self.inprocessing;
+            self.search = Some(search);
+        }
+        self.search.as_mut().unwrap()
+    }
+}
+
+enum Outcome {
+    Satisfiable(LiteralSet),
+    /// Empty failed assumptions mean the problem is unsatisfiable whatever we assume
+    Unsatisfiable {
+        failed_assumptions: Vec<Literal>,
+    },
+    /// We ran out of budget before finding an answer
+    Unknown(Limit),
+}
+
+impl Outcome {
+    fn unsatisfiable() -> Outcome {
+        Outcome::Unsatisfiable {
+            failed_assumptions: vec![],
+        }
+    }
+}
 
-        let mut clause_store = ClauseStore::new(self.clauses.clone());
-        let mut knowledge_graph = KnowledgeGraph::new(self.variables.count());
+/// The state of the search, which persists between calls to solve
+struct Search {
+    clause_store: ClauseStore,
+    knowledge_graph: KnowledgeGraph,
+    trail: Trail,
+    decisions: Box<dyn DecisionStrategy>,
+    phases: SavedPhases,
+    restart_policy: Box<dyn RestartPolicy>,
+    clause_deletion: Box<dyn ClauseDeletionPolicy>,
+    // Undoes preprocessing, for models and for variables constrained again
+    reconstruction: Reconstruction,
+    // Across every call to solve, so that the reduction schedule carries on where it left off
+    conflict_count: usize,
+    inprocessing: bool,
+    next_subsumption: usize,
+    next_probing: usize,
+    next_vivification: usize,
+    // Each round of probing carries on from the variable the last one stopped at
+    next_probe_variable: usize,
+    // Set once we derive a conflict without making any decisions. Nothing can undo that
+    unsatisfiable: bool,
+}
 
-        let initial_assignment = match find_inital_assignment(&clause_store) {
-            InitialAssignmentResult::Conflict(_conflict) => {
-                return Solution {
-                    literals: self.variables.clone(),
-                    solution: None,
-                    stats,
+impl Search {
+    fn new(
+        mut clause_store: ClauseStore,
+        variable_count: usize,
+        decisions: Box<dyn DecisionStrategy>,
+        phases: SavedPhases,
+        restart_policy: Box<dyn RestartPolicy>,
+        clause_deletion: Box<dyn ClauseDeletionPolicy>,
+        reconstruction: Reconstruction,
+    ) -> Search {
+        let mut knowledge_graph = KnowledgeGraph::new(variable_count);
+        let mut trail = Trail::new();
+        let mut unsatisfiable = false;
+        match find_inital_assignment(&clause_store) {
+            InitialAssignmentResult::Conflict(conflict) => {
+                clause_store.learnt_empty_clause(&[
+                    ClauseRef::Unit(conflict.conflicting_literal),
+                    conflict.conflicting_clause,
+                ]);
+                unsatisfiable = true;
+            }
+            InitialAssignmentResult::Assignment(initial_assignment) => {
+                record_initial_assignment(&mut knowledge_graph, &initial_assignment);
+                for lit in initial_assignment {
+                    trail.add_inferred(lit)
                 }
             }
-            InitialAssignmentResult::Assignment(vars) => vars,
         };
+        Search {
+            clause_store,
+            knowledge_graph,
+            trail,
+            decisions,
+            phases,
+            restart_policy,
+            clause_deletion,
+            reconstruction,
+            conflict_count: 0,
+            inprocessing: true,
+            next_subsumption: SUBSUMPTION_INTERVAL,
+            next_probing: 0,
+            next_vivification: VIVIFICATION_INTERVAL,
+            next_probe_variable: 0,
+            unsatisfiable,
+        }
+    }
 
-        record_initial_assignment(&mut clause_store, &mut knowledge_graph, &initial_assignment);
-        let mut trail = Trail::new();
-        for lit in initial_assignment {
-            trail.add_inferred(lit)
+    fn add_variable(&mut self, var: Variable, initial_phase: &InitialPhase) {
+        self.knowledge_graph.add_variable(var);
+        self.decisions.add_variable(var);
+        self.phases.add_variable(var, initial_phase);
+        self.reconstruction.add_variable(var);
+    }
+
+    /// Puts back the clauses preprocessing removed that the variables are involved in, so
+    /// that they can be constrained again. Must be at the root
+    fn restore(&mut self, variables: &[Variable]) {
+        let restored = self.reconstruction.restore(variables);
+        if restored.is_empty() {
+            return;
         }
+        trace!("restoring {} preprocessed clauses", restored.len());
+        // Eliminated variables were dropped from the decisions once, and never put back
+        let literals = restored
+            .iter()
+            .flatten()
+            .copied()
+            .filter(|lit| self.trail.assignment().get(lit.var()).is_none())
+            .collect_vec();
+        self.decisions.unassigned(&literals);
+        for clause in restored {
+            self.add_clause(&clause, false);
+        }
+    }
 
-        stats.initial_unit_count = trail.assignment().size();
+    /// Adds a clause from the root of the search. Literals that are already false there can be
+    /// dropped, and clauses that are already satisfied there can be ignored entirely. Learnt
+    /// clauses may later be deleted again when the learnt clauses are reduced.
+    fn add_clause(&mut self, literals: &[Literal], learnt: bool) {
+        self.rollback(0);
+        if self.unsatisfiable {
+            return;
+        }
+        self.restore(&literals.iter().map(|l| l.var()).collect_vec());
+        if self.unsatisfiable {
+            return;
+        }
+        let assignment = self.trail.assignment();
+        if literals.iter().any(|&l| assignment.contains(l)) {
+            return;
+        }
+        let mut literals = literals
+            .iter()
+            .copied()
+            .filter(|l| !assignment.contains(l.invert()))
+            .collect_vec();
+        sort_and_dedupe(&mut literals);
+        if literals
+            .iter()
+            .tuple_windows()
+            .any(|(a, b)| a.var() == b.var())
+        {
+            // Tautologies are always satisfied
+            return;
+        }
+        match literals.len() {
+            0 => {
+                info!("infeasible due to added clause");
+                self.unsatisfiable = true;
+            }
+            1 => {
+                let lit = literals[0];
+                self.clause_store.add_clause(literals);
+                self.trail.add_inferred(lit);
+                self.knowledge_graph.add_initial(lit);
+            }
+            _ if learnt => {
+                let lbd = literals.len();
+                self.clause_store.add_learnt_clause(literals, lbd, &[]);
+            }
+            _ => {
+                self.clause_store.add_clause(literals);
+            }
+        }
+    }
 
-        if clause_store.idx().all_clauses_resolved() {
-            info!("solved through initial unit assignment");
-            return Solution {
-                literals: self.variables.clone(),
-                solution: Some(trail.assignment().clone()),
-                stats,
-            };
+    /// Adds the clauses the other workers of a portfolio have learnt. Must be at the root
+    fn import_clauses(&mut self, sharing: &mut ClauseSharing) {
+        for clause in sharing.import() {
+            self.add_clause(&clause, true);
         }
+    }
+
+    fn solve(
+        &mut self,
+        backtrack_strategy: &dyn BacktrackStrategy,
+        assumptions: &[Literal],
+        limits: &Limits,
+        mut sharing: Option<&mut ClauseSharing>,
+    ) -> (Outcome, EvaluationStats) {
+        let mut stats = EvaluationStats {
+            step_count: 0,
+            initial_unit_count: 0,
+            unit_prop_count: 0,
+            backtrack_count: 0,
+            learnt_clause_count: 0,
+            restart_count: 0,
+            deleted_clause_count: 0,
+            minimized_literal_count: 0,
+        };
+        if self.unsatisfiable {
+            return (Outcome::unsatisfiable(), stats);
+        }
+        // Start again from the root, where the previous call may have left us
+        self.rollback(0);
+        self.restore(&assumptions.iter().map(|l| l.var()).collect_vec());
+        if let Some(sharing) = sharing.as_deref_mut() {
+            self.import_clauses(sharing);
+        }
+        if self.inprocessing && self.conflict_count >= self.next_probing {
+            self.probe();
+            if !self.unsatisfiable {
+                self.eliminate_pure_literals(assumptions);
+            }
+        }
+        if self.unsatisfiable {
+            return (Outcome::unsatisfiable(), stats);
+        }
+        stats.initial_unit_count = self.trail.assignment().size();
+        let budget = Budget::start(limits);
+        // The assumptions before this one are all true. Only backtracking can undo them
+        let mut next_assumption = 0;
 
         loop {
+            if let Some(limit) = budget.exhausted(
+                stats.backtrack_count,
+                stats.step_count,
+                stats.unit_prop_count,
+            ) {
+                info!("giving up: out of {:?}", limit);
+                return (Outcome::Unknown(limit), stats);
+            }
+            if sharing.as_ref().is_some_and(|sharing| sharing.stopped()) {
+                info!("giving up: another worker finished first");
+                return (Outcome::Unknown(Limit::Interrupted), stats);
+            }
             trace!("========");
             trace!(
                 "iteration starting. level: {}",
-                trail.current_decision_level()
+                self.trail.current_decision_level()
             );
-            let mut ass = trail.assignment().as_assignment_vec();
-            sort_and_dedupe(&mut ass);
-            trace!("assignment: {:?}", ass,);
+            if log_enabled!(Level::Trace) {
+                let mut ass = self.trail.assignment().as_assignment_vec();
+                sort_and_dedupe(&mut ass);
+                trace!("assignment: {:?}", ass,);
+            }
             trace!("========");
 
-            let deduced = trail.assignments_since_last_decision().size();
-            let mut unit_prop =
-                UnitPropagator::new(&mut clause_store, &mut trail, &mut knowledge_graph);
-            let prop_eval_result = unit_prop.propagate_units().or_else(|| unit_prop.evaluate());
-            stats.unit_prop_count += trail.assignments_since_last_decision().size() - deduced;
+            let deduced = self.trail.assignment().size();
+            let mut unit_prop = UnitPropagator::new(
+                &mut self.clause_store,
+                &mut self.trail,
+                &mut self.knowledge_graph,
+            );
+            let prop_eval_result = unit_prop.propagate_units();
+            stats.unit_prop_count += self.trail.assignment().size() - deduced;
 
             if let Some(conflict) = prop_eval_result {
-                if trail.current_decision_level() == 0 {
+                // Once chronological backtracking has kept literals below the current decision
+                // level, the conflicting clause can be falsified entirely below it. The
+                // conflict is at the highest level of its literals
+                let (conflict_level, at_conflict_level) =
+                    self.falsified_level(conflict.conflicting_clause);
+                if conflict_level == 0 {
                     info!("conflict in decision level 0: {:?}", conflict);
-                    return self.infeasible(stats);
+                    let hints = self.hints(conflict.conflicting_clause, &[]);
+                    self.clause_store.learnt_empty_clause(&hints);
+                    self.unsatisfiable = true;
+                    return (Outcome::unsatisfiable(), stats);
+                }
+                if conflict_level < self.trail.current_decision_level() {
+                    self.rollback(conflict_level);
+                }
+                if let &[lit] = &at_conflict_level[..] {
+                    // The clause was unit below the conflict level, and we missed it. That is
+                    // no conflict: the literal should have been implied there
+                    self.rollback(conflict_level - 1);
+                    self.imply_missed(lit, conflict.conflicting_clause);
+                    next_assumption = 0;
+                    continue;
                 }
 
                 trace!("conflict: {:?}", conflict);
+                self.conflict_count += 1;
                 let analyzer = ConflictAnalyzer::default();
                 let analyzed_conflict = analyzer
-                    .analyse_conflict(&clause_store, &trail, &knowledge_graph, &conflict)
+                    .analyse_conflict(
+                        &self.clause_store,
+                        &self.trail,
+                        &self.knowledge_graph,
+                        &conflict,
+                    )
                     .unwrap();
                 trace!("analyzed_conflict: {:?}", analyzed_conflict);
+                let hints = self.hints(
+                    conflict.conflicting_clause,
+                    &analyzed_conflict.learnt_clause,
+                );
+                stats.minimized_literal_count += analyzed_conflict.minimized_literal_count;
+                self.decisions.bump(&analyzed_conflict.involved_variables);
+                self.decisions.decay();
+                for &clause in analyzed_conflict.antecedents.iter() {
+                    self.clause_store.bump_activity(clause);
+                }
+                self.clause_store.decay_activity();
+                self.restart_policy.on_conflict(analyzed_conflict.lbd);
 
-                self.backtrack(
-                    &conflict,
-                    &analyzed_conflict,
-                    &mut trail,
-                    &mut clause_store,
-                    &mut knowledge_graph,
-                )
-                .unwrap();
+                self.backtrack(backtrack_strategy, &conflict, &analyzed_conflict)
+                    .unwrap();
                 stats.backtrack_count += 1;
 
-                if let Some(clause) =
-                    clause_store.add_clause(analyzed_conflict.learnt_clause.clone())
+                // Watch the asserting literal, and the most recently assigned of the rest. They
+                // are the first two, and whatever we backtracked over comes before the rest
+                let learnt_clause = analyzed_conflict.learnt_clause.clone();
+                let clause = self.clause_store.add_learnt_clause(
+                    learnt_clause.clone(),
+                    analyzed_conflict.lbd,
+                    &hints,
+                );
+                stats.learnt_clause_count += 1;
+                if let Some(sharing) = sharing.as_deref() {
+                    sharing.export(&learnt_clause);
+                }
+                if clause.is_unit() {
+                    let lit = clause.unit();
+                    if self.trail.assignment().get(lit.var()) == Some(lit.invert()) {
+                        info!("infeasible due to conflicting learnt unit clause");
+                        let hints = self.hints(clause, &[]);
+                        self.clause_store.learnt_empty_clause(&hints);
+                        self.unsatisfiable = true;
+                        return (Outcome::unsatisfiable(), stats);
+                    }
+                    self.trail.add_inferred_at(lit, 0);
+                    self.knowledge_graph.add_initial(lit);
+                } else if analyzed_conflict.second_highest_decision_level
+                    <= self.trail.current_decision_level()
+                    && self
+                        .trail
+                        .assignment()
+                        .get(learnt_clause[0].var())
+                        .is_none()
                 {
-                    stats.learnt_clause_count += 1;
-                    if clause.is_unit() {
-                        let lit = clause.unit();
-                        if trail.assignment().get(lit.var()) == Some(lit.invert()) {
-                            info!("infeasible due to conflicting learnt unit clause");
-                            return self.infeasible(stats);
-                        }
-                        trail.add_inferred(lit);
-                        knowledge_graph.add_initial(lit);
-                        clause_store.mark_resolved(lit.var());
+                    // The learnt clause is unit after backjumping. Its watches will never
+                    // see the literals that made it so, so assign the asserting literal here.
+                    // It belongs at the level we would have backjumped to, which is below the
+                    // current one if we backtracked chronologically. A strategy that went
+                    // back further than that leaves the clause with two literals to watch
+                    let lit = learnt_clause[0];
+                    let level = analyzed_conflict.second_highest_decision_level;
+                    self.trail.add_inferred_at(lit, level);
+                    self.knowledge_graph.add_inferred(
+                        lit,
+                        learnt_clause[1].invert(),
+                        self.trail.decision_at(level),
+                        clause,
+                    );
+                }
+                next_assumption = 0;
+                continue;
+            }
+
+            if self.restart_policy.should_restart() && self.trail.current_decision_level() > 0 {
+                info!("restarting");
+                self.rollback(0);
+                next_assumption = 0;
+                if let Some(sharing) = sharing.as_deref_mut() {
+                    self.import_clauses(sharing);
+                    if self.unsatisfiable {
+                        return (Outcome::unsatisfiable(), stats);
+                    }
+                }
+                let mut simplified = false;
+                if self.inprocessing && self.conflict_count >= self.next_subsumption {
+                    self.next_subsumption = self.conflict_count + SUBSUMPTION_INTERVAL;
+                    stats.deleted_clause_count += self.subsume_learnt_clauses();
+                    simplified = true;
+                }
+                if self.inprocessing && self.conflict_count >= self.next_probing {
+                    self.probe();
+                    if self.unsatisfiable {
+                        return (Outcome::unsatisfiable(), stats);
+                    }
+                    simplified = true;
+                }
+                if self.inprocessing && self.conflict_count >= self.next_vivification {
+                    stats.deleted_clause_count += self.vivify();
+                    if self.unsatisfiable {
+                        return (Outcome::unsatisfiable(), stats);
                     }
+                    simplified = true;
                 }
+                // Simplifying can leave variables pure
+                if simplified {
+                    stats.deleted_clause_count += self.eliminate_pure_literals(assumptions);
+                }
+                self.restart_policy.restarted();
+                stats.restart_count += 1;
                 continue;
             }
 
-            if clause_store.idx().all_clauses_resolved() {
-                return Solution {
-                    literals: self.variables.clone(),
-                    solution: Some(trail.assignment().clone()),
-                    stats,
-                };
+            if self.clause_deletion.due(self.conflict_count) {
+                let deleted = self.select_deleted_clauses();
+                info!(
+                    "reducing learnt clauses: deleting {} of {}",
+                    deleted.len(),
+                    self.clause_store.learnt_clause_count()
+                );
+                let moved = self.clause_store.delete_clauses(&deleted);
+                self.knowledge_graph.remap_clauses(&moved);
+                stats.deleted_clause_count += deleted.len();
             }
 
-            // Now, keep stepping into the problem
-            if let Some(&var) = traversal_plan.next(&trail) {
-                let lit = Literal::new(var, true);
+            // The assumptions are decided before anything else, in order
+            let assignment = self.trail.assignment();
+            while next_assumption < assumptions.len()
+                && assignment.contains(assumptions[next_assumption])
+            {
+                next_assumption += 1;
+            }
+            if let Some(&assumption) = assumptions.get(next_assumption) {
+                if assignment.contains(assumption.invert()) {
+                    info!("assumption {:?} cannot hold", assumption);
+                    let failed_assumptions = self.failed_assumptions(assumptions, assumption);
+                    return (Outcome::Unsatisfiable { failed_assumptions }, stats);
+                }
                 stats.step_count += 1;
-                trail.add_decision(lit);
-                knowledge_graph.add_decision(lit);
-                clause_store.mark_resolved(var)
-            } else {
-                // If we can't keep going, we're done, i guess. Iterate one more time
+                self.trail.add_decision(assumption);
+                self.knowledge_graph.add_decision(assumption);
                 continue;
             }
+
+            // Now, keep stepping into the problem. Once every variable has been assigned
+            // without conflict, every clause is satisfied
+            let var = loop {
+                match self.decisions.next(&self.trail) {
+                    Some(var)
+                        if self.reconstruction.is_eliminated(var)
+                            || self.trail.value(var).is_some() =>
+                    {
+                        continue
+                    }
+                    Some(var) => break var,
+                    None => {
+                        let mut assignment = self.trail.assignment().clone();
+                        self.reconstruction.extend(&mut assignment);
+                        return (Outcome::Satisfiable(assignment), stats);
+                    }
+                }
+            };
+            let lit = self.phases.decide(var);
+            stats.step_count += 1;
+            self.trail.add_decision(lit);
+            self.knowledge_graph.add_decision(lit);
         }
     }
 
+    /// The learnt clauses the clause deletion policy picks, other than those we must keep. Units
+    /// and pairs are always kept, as they are told apart by their literals alone: deleting one
+    /// could delete an original clause with the same literals instead
+    fn select_deleted_clauses(&mut self) -> FnvHashSet<ClauseRef> {
+        let locked = self.knowledge_graph.reasons();
+        let (clauses, learnts): (Vec<_>, Vec<_>) = self
+            .clause_store
+            .learnt_clauses()
+            .filter(|(clause, _)| matches!(clause, ClauseRef::Long { .. }))
+            .map(|(clause, meta)| {
+                let learnt = LearntClause {
+                    len: clause.len(),
+                    lbd: meta.lbd,
+                    activity: meta.activity,
+                    locked: locked.contains(&clause),
+                };
+                (clause, learnt)
+            })
+            .unzip();
+        self.clause_deletion
+            .select(&learnts)
+            .into_iter()
+            .filter(|&ix| !learnts[ix].locked)
+            .map(|ix| clauses[ix])
+            .collect()
+    }
+
+    /// Deletes the learnt clauses that other clauses subsume, and replaces those they strengthen
+    /// with the shorter clause. Must be at the root. Returns how many clauses were deleted
+    fn subsume_learnt_clauses(&mut self) -> usize {
+        let result = subsume_learnt_clauses(&self.clause_store, self.trail.assignment());
+        info!(
+            "subsumption: deleting {} learnt clauses, strengthening {}",
+            result.subsumed.len(),
+            result.strengthened.len()
+        );
+        let mut deleted = result.subsumed;
+        for strengthened in result.strengthened {
+            // Only learnt clauses with no assigned literals are strengthened, so any literals
+            // can be watched
+            let clause = self.clause_store.add_learnt_clause(
+                strengthened.literals,
+                strengthened.lbd,
+                &[strengthened.by, strengthened.clause],
+            );
+            if clause.is_unit() {
+                self.trail.add_inferred(clause.unit());
+                self.knowledge_graph.add_initial(clause.unit());
+            }
+            deleted.insert(strengthened.clause);
+        }
+        let moved = self.clause_store.delete_clauses(&deleted);
+        self.knowledge_graph.remap_clauses(&moved);
+        deleted.len()
+    }
+
+    /// Fixes the failed literals found by probing from the root, along with the literals both
+    /// polarities of a variable imply. Must be at the root
+    fn probe(&mut self) {
+        self.next_probing = self.conflict_count + PROBING_INTERVAL;
+        let variable_count = self.knowledge_graph.variable_count();
+        let start = self.next_probe_variable;
+        let reconstruction = &self.reconstruction;
+        let variables = (0..variable_count)
+            .map(|ix| Variable(((start + ix) % variable_count) as u64))
+            .filter(|&var| !reconstruction.is_eliminated(var));
+        let probed = Prober::new(
+            &mut self.clause_store,
+            &mut self.trail,
+            &mut self.knowledge_graph,
+        )
+        .probe(variables, PROBING_BUDGET);
+        if let Some(var) = probed.last_probed {
+            self.next_probe_variable = (var.idx() + 1) % variable_count;
+        }
+        self.unsatisfiable |= probed.unsatisfiable;
+    }
+
+    /// Replaces learnt clauses with the shorter clauses vivification finds. Must be at the root.
+    /// Returns how many clauses were deleted
+    fn vivify(&mut self) -> usize {
+        self.next_vivification = self.conflict_count + VIVIFICATION_INTERVAL;
+        let (vivified, replaced) = Vivifier::new(
+            &mut self.clause_store,
+            &mut self.trail,
+            &mut self.knowledge_graph,
+        )
+        .vivify(VIVIFICATION_BUDGET);
+        self.unsatisfiable |= vivified.unsatisfiable;
+        let moved = self.clause_store.delete_clauses(&replaced);
+        self.knowledge_graph.remap_clauses(&moved);
+        replaced.len()
+    }
+
+    /// Pure literal elimination, as preprocessing does it, over the clauses not yet satisfied at
+    /// the root, learnt ones included. The problem clauses of a pure literal go onto the
+    /// reconstruction stack rather than fixing it at the root, so that assumptions and clauses
+    /// added later can constrain the variable again. Assumed variables are left alone. Must be
+    /// at the root. Returns how many clauses were removed
+    fn eliminate_pure_literals(&mut self, assumptions: &[Literal]) -> usize {
+        // The proof has no way to bring back an original clause once it is deleted
+        if self.clause_store.has_proof() {
+            return 0;
+        }
+        let variable_count = self.knowledge_graph.variable_count();
+        let assignment = self.trail.assignment();
+        let clauses = self
+            .clause_store
+            .iter_with_meta()
+            .map(|(clause, meta)| {
+                let literals = self.clause_store.clause_literals(clause).collect_vec();
+                (clause, meta.learnt, literals)
+            })
+            .filter(|(_, _, literals)| !literals.iter().any(|&lit| assignment.contains(lit)))
+            .collect_vec();
+        // The clauses each unassigned literal occurs in, indexed by Literal::idx
+        let mut occurrences = vec![vec![]; 2 * variable_count];
+        for (ix, (_, _, literals)) in clauses.iter().enumerate() {
+            for lit in literals {
+                if !assignment.contains_var(lit.var()) {
+                    occurrences[lit.idx()].push(ix);
+                }
+            }
+        }
+
+        let mut excluded = vec![false; variable_count];
+        for lit in assumptions {
+            excluded[lit.var().idx()] = true;
+        }
+        let mut removed = vec![false; clauses.len()];
+        let mut queue = (0..variable_count as u64).map(Variable).collect_vec();
+        let mut queued = vec![true; variable_count];
+        let mut removed_count = 0;
+        while let Some(var) = queue.pop() {
+            queued[var.idx()] = false;
+            if excluded[var.idx()] || self.reconstruction.is_eliminated(var) {
+                continue;
+            }
+            let positive = Literal::new(var, true);
+            let occurs = |lit: Literal| occurrences[lit.idx()].iter().any(|&ix| !removed[ix]);
+            let pure = match (occurs(positive), occurs(positive.invert())) {
+                (true, false) => positive,
+                (false, true) => positive.invert(),
+                // Variables in no clauses are left to the search
+                _ => continue,
+            };
+
+            for &ix in occurrences[pure.idx()].iter() {
+                if removed[ix] {
+                    continue;
+                }
+                removed[ix] = true;
+                removed_count += 1;
+                let (_, learnt, literals) = &clauses[ix];
+                if !learnt {
+                    self.reconstruction.push(pure, literals.clone());
+                }
+                for lit in literals {
+                    if !queued[lit.var().idx()] {
+                        queued[lit.var().idx()] = true;
+                        queue.push(lit.var());
+                    }
+                }
+            }
+            self.reconstruction.eliminate(var);
+        }
+        info!("inprocessing: eliminated pure literals from {removed_count} clauses");
+
+        let deleted = clauses
+            .iter()
+            .zip(removed)
+            .filter(|(_, removed)| *removed)
+            .map(|((clause, _, _), _)| *clause)
+            .collect::<FnvHashSet<_>>();
+        let moved = self.clause_store.delete_clauses(&deleted);
+        self.knowledge_graph.remap_clauses(&moved);
+        removed_count
+    }
+
+    fn hints(&self, conflicting_clause: ClauseRef, clause: &[Literal]) -> Vec<ClauseRef> {
+        hints(
+            &self.clause_store,
+            &self.trail,
+            &self.knowledge_graph,
+            conflicting_clause,
+            clause,
+        )
+    }
+
+    /// The highest decision level of the literals of a falsified clause, and which of its
+    /// literals were assigned there
+    fn falsified_level(&self, clause: ClauseRef) -> (usize, Vec<Literal>) {
+        let levels = self
+            .clause_store
+            .clause_literals(clause)
+            .map(|l| (l, self.trail.find_decision_level(l.var()).unwrap()))
+            .collect_vec();
+        let level = levels.iter().map(|&(_, level)| level).max().unwrap_or(0);
+        let literals = levels
+            .into_iter()
+            .filter(|&(_, l)| l == level)
+            .map(|(lit, _)| lit)
+            .collect();
+        (level, literals)
+    }
+
+    /// Assigns the literal of the clause, which the clause's other literals imply. They are all
+    /// false, and the highest of their levels is where the literal goes.
+    fn imply_missed(&mut self, literal: Literal, clause: ClauseRef) {
+        let (trigger, level) = self
+            .clause_store
+            .clause_literals(clause)
+            .filter(|&l| l != literal)
+            .map(|l| (l.invert(), self.trail.find_decision_level(l.var()).unwrap()))
+            .max_by_key(|&(_, level)| level)
+            .unwrap();
+        self.trail.add_inferred_at(literal, level);
+        self.knowledge_graph
+            .add_inferred(literal, trigger, self.trail.decision_at(level), clause);
+    }
+
+    /// The assumptions that together force the failed assumption to be false, along with the
+    /// failed assumption itself
+    fn failed_assumptions(&self, assumptions: &[Literal], failed: Literal) -> Vec<Literal> {
+        // While some assumption is not yet true, every decision made is an assumption
+        let decisions = ConflictAnalyzer::default().analyse_final(
+            &self.clause_store,
+            &self.trail,
+            &self.knowledge_graph,
+            failed.invert(),
+        );
+        assumptions
+            .iter()
+            .copied()
+            .filter(|&l| l == failed || decisions.contains(&l))
+            .unique()
+            .collect()
+    }
+
     fn backtrack(
-        &self,
+        &mut self,
+        backtrack_strategy: &dyn BacktrackStrategy,
         conflict: &Conflict,
         analyzed_conflict: &AnalyzedConflict,
-        path: &mut Trail,
-        clause_store: &mut ClauseStore,
-        knowledge_graph: &mut KnowledgeGraph,
     ) -> Option<()> {
         // Attempt to find the position that should be pivoted on. if we cannot find such a point, we have failed to backtrack
-        let pivot = match self.backtrack_strategy.find_backtrack_point(
-            path.search_path(),
+        let pivot = match backtrack_strategy.find_backtrack_point(
+            self.trail.search_path(),
             conflict,
             analyzed_conflict,
         ) {
             None => panic!("backtrack failed"),
             Some(pivot) => pivot,
         };
-        let backtracked = path.backtrack(pivot);
-
-        // Rollback the assignments
-        for lit in backtracked.assignments.iter() {
-            clause_store.mark_unresolved(lit.var());
-        }
-        knowledge_graph.remove(&backtracked.assignments);
+        assert!(
+            pivot < self.trail.current_decision_level(),
+            "backtrack strategy must undo the level of the conflict"
+        );
+        self.rollback(pivot);
 
         Some(())
     }
 
-    fn infeasible(&self, stats: EvaluationStats) -> Solution {
-        Solution {
-            literals: self.variables.clone(),
-            solution: None,
-            stats,
-        }
+    /// Drops every decision level above the pivot
+    fn rollback(&mut self, pivot: usize) {
+        let backtracked = self.trail.backtrack(pivot);
+
+        // Rollback the assignments. The watches are still valid, so the clauses need no work
+        self.knowledge_graph.remove(&backtracked.assignments);
+        self.decisions.unassigned(&backtracked.assignments);
+        self.phases.save(&backtracked.assignments);
     }
 }
 
@@ -225,40 +1123,60 @@ pub struct EvaluationStats {
     unit_prop_count: usize,
     backtrack_count: usize,
     learnt_clause_count: usize,
+    restart_count: usize,
+    deleted_clause_count: usize,
+    minimized_literal_count: usize,
+}
+
+/// What a call to solve found
+#[derive(Debug, Clone, PartialEq)]
+pub enum SolveResult {
+    Sat(Model),
+    Unsat,
+    /// The solve gave up once it hit the limit
+    Unknown(Limit),
+    /// The model found does not satisfy the clauses, which is a bug in the solver. Only
+    /// checked when `Instance::set_verify_models` is on.
+    InvalidModel {
+        model: Model,
+        error: ModelError,
+    },
 }
 
 #[derive(Clone)]
 pub struct Solution {
-    pub literals: Rc<VariableRegister>,
-    pub(crate) solution: Option<LiteralSet>,
+    pub result: SolveResult,
+    failed_assumptions: Vec<Literal>,
     pub stats: EvaluationStats,
 }
 
 impl Solution {
+    /// The satisfying assignment, if one was found
+    pub fn model(&self) -> Option<&Model> {
+        match &self.result {
+            SolveResult::Sat(model) => Some(model),
+            _ => None,
+        }
+    }
+
     pub fn assignments(&self) -> Option<Vec<Literal>> {
-        self.solution.clone().map(|ls| ls.as_assignment_vec())
+        self.model().map(|model| model.literals())
+    }
+
+    /// When unsatisfiable under assumptions, a subset of the assumptions that cannot all hold
+    /// together. Empty if satisfiable, or if unsatisfiable whatever the assumptions.
+    pub fn failed_assumptions(&self) -> &[Literal] {
+        &self.failed_assumptions
     }
 }
 
 impl fmt::Debug for Solution {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        if let Some(solution) = &self.solution {
-            let mut first = true;
-            for lit in self.literals.iter_original() {
-                if !first {
-                    write!(f, ", ")?;
-                }
-                first = false;
-
-                let name = self.literals.get(lit);
-                let formatted_val = match solution.get(lit) {
-                    Some(val) => format!("{:?}", val),
-                    None => "undef".to_string(),
-                };
-                write!(f, "{}={}", name, formatted_val)?;
-            }
-        } else {
-            write!(f, "no solution found")?;
+        match &self.result {
+            SolveResult::Sat(model) => write!(f, "{:?}", model)?,
+            SolveResult::Unsat => write!(f, "no solution found")?,
+            SolveResult::Unknown(limit) => write!(f, "gave up: out of {:?}", limit)?,
+            SolveResult::InvalidModel { error, .. } => write!(f, "invalid model: {}", error)?,
         }
         write!(f, "; stats={:?}", self.stats)?;
         Ok(())
@@ -267,9 +1185,24 @@ impl fmt::Debug for Solution {
 
 #[cfg(test)]
 mod test {
+    use std::collections::{BTreeSet, HashMap};
+    use std::fs;
+    use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+    use std::sync::Arc;
+
     use crate::{
         problem_builder::ProblemBuilder,
-        solver::{assignment_set::LiteralSet, Instance},
+        solver::{
+            assignment_set::LiteralSet, AnalyzedConflict, BacktrackStrategy, Backtracking,
+            ClauseDeletion, ClauseDeletionPolicy, Conflict, DecisionStrategy, Decisions,
+            InitialPhase, Instance, LearntClause, Limit, Limits, ModelError, RestartPolicy,
+            Restarts, SolveResult, SolverConfig, Trail, TrailEntry,
+        },
+        solver::{
+            proof::test::{check_lrat_proof, dimacs_clauses},
+            test::{clauses, literals},
+            ProofFormat,
+        },
         variable_registry::VariableRegister,
         *,
     };
@@ -306,7 +1239,7 @@ mod test {
 
         let mut instance = pb.build();
         let solution = instance.solve();
-        assert!(solution.solution.is_none());
+        assert!(solution.model().is_none());
         println!("{:?}", solution);
         // assert!(false);
     }
@@ -340,7 +1273,7 @@ mod test {
 
         let mut instance = pb.build();
         let solution = instance.solve();
-        assert!(solution.solution.is_some());
+        assert!(solution.model().is_some());
         println!("{:?}", solution);
     }
 
@@ -351,9 +1284,9 @@ mod test {
         let b = vr.create_original("b");
         let c = vr.create_original("c");
         let clauses = vec![
-            Clause::new(&vec![Literal::new(a, true)]),
-            Clause::new(&vec![Literal::new(a, false), Literal::new(b, true)]),
-            Clause::new(&vec![Literal::new(b, false), Literal::new(c, true)]),
+            Clause::new(&[Literal::new(a, true)]),
+            Clause::new(&[Literal::new(a, false), Literal::new(b, true)]),
+            Clause::new(&[Literal::new(b, false), Literal::new(c, true)]),
         ];
 
         let mut instance = Instance::new_from_clauses(clauses, vr);
@@ -363,7 +1296,7 @@ mod test {
         expected.add(Literal::new(a, true));
         expected.add(Literal::new(b, true));
         expected.add(Literal::new(c, true));
-        assert_eq!(solution.solution, Some(expected));
+        assert_eq!(solution.model().map(|m| &m.assignment), Some(&expected));
     }
 
     // This test requires the solver to step into a=true, and then use unit prop to resolve the other variables
@@ -374,22 +1307,24 @@ mod test {
         let b = vr.create_original("b");
         let c = vr.create_original("c");
         let clauses = vec![
-            Clause::new(&vec![Literal::new(a, true), Literal::new(b, true)]),
-            Clause::new(&vec![Literal::new(a, false), Literal::new(b, true)]),
-            Clause::new(&vec![Literal::new(b, false), Literal::new(c, true)]),
+            Clause::new(&[Literal::new(a, true), Literal::new(b, true)]),
+            Clause::new(&[Literal::new(a, false), Literal::new(b, true)]),
+            Clause::new(&[Literal::new(b, false), Literal::new(c, true)]),
         ];
 
         let mut instance = Instance::new_from_clauses(clauses, vr);
+        instance.set_preprocessing(false);
         let solution = instance.solve();
 
         let mut expected = LiteralSet::new();
         expected.add(Literal::new(a, true));
         expected.add(Literal::new(b, true));
         expected.add(Literal::new(c, true));
-        assert_eq!(solution.solution, Some(expected));
+        assert_eq!(solution.model().map(|m| &m.assignment), Some(&expected));
     }
 
-    // This test requires the solver to step into a=true, hit conflicts, backtrack, and then try a=false
+    // a=true implies both b=false and c=false, so the solver has to backtrack to a=false. b
+    // keeps the phase it was saved with, and c has to be true
     #[test]
     fn test_build_and_solve_feasible_backtrack() {
         // env_logger::init();
@@ -403,18 +1338,484 @@ mod test {
         let b = Literal::new(vb, true);
         let c = Literal::new(vc, true);
         let clauses = vec![
-            Clause::new(&vec![a.invert(), b.invert()]),
-            Clause::new(&vec![a.invert(), c.invert()]),
-            Clause::new(&vec![b, c]),
+            Clause::new(&[a.invert(), b.invert()]),
+            Clause::new(&[a.invert(), c.invert()]),
+            Clause::new(&[b, c]),
         ];
 
         let mut instance = Instance::new_from_clauses(clauses, vr);
+        // Preprocessing leaves no clauses at all, and a model of its own, and probing finds
+        // a=false before the search does
+        instance.set_preprocessing(false);
+        instance.set_inprocessing(false);
         let solution = instance.solve();
+        assert_eq!(solution.stats.backtrack_count, 1);
 
         let mut expected = LiteralSet::new();
         expected.add(a.invert());
-        expected.add(b);
+        expected.add(b.invert());
         expected.add(c);
-        assert_eq!(solution.solution, Some(expected));
+        assert_eq!(solution.model().map(|m| &m.assignment), Some(&expected));
+    }
+
+    // Nothing constrains the polarity of b, so it takes the initial phase
+    #[test]
+    fn test_solve_with_initial_phase() {
+        let mut vr = VariableRegister::new();
+        let a = vr.create_original("a");
+        let b = vr.create_original("b");
+        let clauses = vec![Clause::new(&[Literal::new(a, true), Literal::new(b, true)])];
+
+        let mut instance = Instance::new_from_clauses(clauses.clone(), vr.clone());
+        instance.set_preprocessing(false);
+        instance.set_initial_phase(InitialPhase::False);
+        let solution = instance.solve();
+        let expected =
+            LiteralSet::from_assignment_vec(&vec![Literal::new(a, false), Literal::new(b, true)]);
+        assert_eq!(solution.model().map(|m| &m.assignment), Some(&expected));
+
+        let mut instance = Instance::new_from_clauses(clauses, vr);
+        instance.set_preprocessing(false);
+        instance.set_initial_phase(InitialPhase::PerVariable(HashMap::from([(b, false)])));
+        let solution = instance.solve();
+        let expected =
+            LiteralSet::from_assignment_vec(&vec![Literal::new(a, true), Literal::new(b, false)]);
+        assert_eq!(solution.model().map(|m| &m.assignment), Some(&expected));
+    }
+
+    #[test]
+    fn test_solve_with_restarts() {
+        for restarts in [
+            Restarts::Never,
+            Restarts::Luby { unit: 1 },
+            // Taken as one, rather than restarting before every decision
+            Restarts::Luby { unit: 0 },
+            Restarts::Glucose,
+        ] {
+            // Preprocessing alone refutes dubois20, leaving nothing to restart
+            let mut instance = dimacs::parse("examples/problem_specs/unsat/dubois20.cnf").unwrap();
+            instance.set_preprocessing(false);
+            instance.set_restarts(restarts);
+            let solution = instance.solve();
+            assert!(solution.model().is_none());
+            if restarts == Restarts::Never {
+                assert_eq!(solution.stats.restart_count, 0);
+            }
+            if let Restarts::Luby { .. } = restarts {
+                assert!(solution.stats.restart_count > 0);
+            }
+
+            let mut instance =
+                dimacs::parse("examples/problem_specs/sat/aim-50-1_6-yes1-4.cnf").unwrap();
+            instance.set_preprocessing(false);
+            instance.set_restarts(restarts);
+            assert!(instance.solve().model().is_some());
+        }
+    }
+
+    #[test]
+    fn test_solve_with_chronological_backtracking() {
+        // A threshold of zero backtracks chronologically after every conflict
+        let backtracking = Backtracking::Chronological { threshold: 0 };
+        let mut instance = dimacs::parse("examples/problem_specs/unsat/dubois20.cnf").unwrap();
+        instance.set_preprocessing(false);
+        instance.set_backtracking(backtracking);
+        assert!(instance.solve().model().is_none());
+
+        for path in [
+            "examples/problem_specs/sat/aim-50-1_6-yes1-4.cnf",
+            "examples/problem_specs/sat/logistics.a.cnf",
+        ] {
+            let mut instance = dimacs::parse(path).unwrap();
+            instance.set_preprocessing(false);
+            instance.set_backtracking(backtracking);
+            // Models are verified in debug builds
+            assert!(instance.solve().model().is_some());
+        }
+    }
+
+    #[test]
+    fn test_solve_with_built_in_strategies() {
+        let config = || {
+            SolverConfig::new()
+                .backtracking(Backtracking::Chronological { threshold: 10 })
+                .decisions(Decisions::InOrder)
+                .restarts(Restarts::Never)
+                .clause_deletion(ClauseDeletion::Never)
+        };
+        let mut instance = dimacs::parse("examples/problem_specs/unsat/dubois20.cnf").unwrap();
+        assert!(instance.solve_with(config()).model().is_none());
+
+        // In order decisions have to pass over the variables preprocessing eliminates
+        let mut instance =
+            dimacs::parse("examples/problem_specs/sat/aim-50-1_6-yes1-4.cnf").unwrap();
+        assert!(instance.solve_with(config()).model().is_some());
+    }
+
+    /// Branches on the last variable first
+    #[derive(Clone)]
+    struct LastFirst {
+        variables: BTreeSet<Variable>,
+    }
+
+    impl DecisionStrategy for LastFirst {
+        fn next(&mut self, _trail: &Trail) -> Option<Variable> {
+            self.variables.pop_last()
+        }
+
+        fn unassigned(&mut self, literals: &[Literal]) {
+            self.variables.extend(literals.iter().map(|lit| lit.var()));
+        }
+
+        fn add_variable(&mut self, var: Variable) {
+            self.variables.insert(var);
+        }
+    }
+
+    /// Restarts every hundred conflicts, counting them
+    #[derive(Clone)]
+    struct EveryHundredConflicts {
+        conflicts: Arc<AtomicUsize>,
+        since_restart: usize,
+    }
+
+    impl RestartPolicy for EveryHundredConflicts {
+        fn on_conflict(&mut self, _lbd: usize) {
+            self.conflicts.fetch_add(1, Ordering::Relaxed);
+            self.since_restart += 1;
+        }
+
+        fn should_restart(&self) -> bool {
+            self.since_restart >= 100
+        }
+
+        fn restarted(&mut self) {
+            self.since_restart = 0;
+        }
+    }
+
+    /// Undoes only the level of the conflict
+    struct OneLevel {}
+
+    impl BacktrackStrategy for OneLevel {
+        fn find_backtrack_point(
+            &self,
+            path: &[TrailEntry],
+            _conflict: &Conflict,
+            _analyzed_conflict: &AnalyzedConflict,
+        ) -> Option<usize> {
+            Some(path.len() - 2)
+        }
+    }
+
+    /// Tries to delete every learnt clause, every thousand conflicts
+    #[derive(Clone)]
+    struct DeleteEverything {
+        next: usize,
+    }
+
+    impl ClauseDeletionPolicy for DeleteEverything {
+        fn due(&self, conflicts: usize) -> bool {
+            conflicts >= self.next
+        }
+
+        fn select(&mut self, learnts: &[LearntClause]) -> Vec<usize> {
+            self.next += 1000;
+            (0..learnts.len()).collect()
+        }
+    }
+
+    #[test]
+    fn test_solve_with_custom_strategies() {
+        let conflicts = Arc::new(AtomicUsize::new(0));
+        let config = || {
+            SolverConfig::new()
+                .backtrack_strategy(OneLevel {})
+                .decision_strategy(LastFirst {
+                    variables: BTreeSet::new(),
+                })
+                .restart_policy(EveryHundredConflicts {
+                    conflicts: conflicts.clone(),
+                    since_restart: 0,
+                })
+                .clause_deletion_policy(DeleteEverything { next: 0 })
+        };
+        let mut instance = dimacs::parse("examples/problem_specs/unsat/dubois20.cnf").unwrap();
+        // Preprocessing leaves nothing for the search
+        instance.set_preprocessing(false);
+        assert!(instance.solve_with(config()).model().is_none());
+        assert!(conflicts.load(Ordering::Relaxed) > 0);
+
+        // Clones search with them too, and so does the model counter, which solves a clone
+        let counted = conflicts.load(Ordering::Relaxed);
+        assert!(instance.clone().solve().model().is_none());
+        assert!(conflicts.load(Ordering::Relaxed) > counted);
+        let counted = conflicts.load(Ordering::Relaxed);
+        assert_eq!(instance.count_models().unwrap(), 0u32.into());
+        assert!(conflicts.load(Ordering::Relaxed) > counted);
+
+        // Once there is a search, the strategies are swapped into it
+        let mut instance = dimacs::parse("examples/problem_specs/sat/logistics.a.cnf").unwrap();
+        assert!(instance.solve().model().is_some());
+        let solution = instance.solve_with(config());
+        // Models are verified in debug builds
+        assert!(solution.model().is_some());
+    }
+
+    // The policy only gets to pick long clauses: a learnt binary clause is told apart from an
+    // original one with the same literals by its literals alone
+    #[test]
+    fn test_delete_everything_with_duplicate_binary_clause() {
+        let file = tempfile::NamedTempFile::new().unwrap();
+        let mut instance = dimacs::parse("examples/problem_specs/unsat/dubois20.cnf").unwrap();
+        let [a, b] = literals();
+        instance.add_clause(&[a, b]);
+        instance.set_proof(file.reopen().unwrap(), ProofFormat::Lrat);
+        instance.set_inprocessing(false);
+        instance
+            .configure(SolverConfig::new().clause_deletion_policy(DeleteEverything { next: 0 }));
+        let clause_store = &mut instance.search().clause_store;
+        let original = clause_store
+            .iter()
+            .find(|clause| clause.len() == 2)
+            .unwrap();
+        clause_store.add_learnt_clause(vec![a, b], 2, &[original]);
+
+        assert!(instance.solve().model().is_none());
+        instance.finish_proof().unwrap();
+        let proof = fs::read_to_string(file.path()).unwrap();
+        assert!(check_lrat_proof(dimacs_clauses(&instance), &proof));
+    }
+
+    #[test]
+    fn test_inprocessing_pure_literals() {
+        let mut vr = VariableRegister::new();
+        let [a, b, c] = ["a", "b", "c"].map(|name| Literal::new(vr.create_original(name), true));
+        // a is pure, and once its clauses go, so is one of b and c
+        let problem = clauses(&[vec![a, b], vec![a, b.invert(), c], vec![b, c.invert()]]);
+        let mut instance = Instance::new_from_clauses(problem, vr);
+        instance.set_preprocessing(false);
+        instance.set_verify_models(true);
+
+        let search = instance.search();
+        // Assumed variables are left alone
+        assert_eq!(search.eliminate_pure_literals(&[a]), 0);
+        assert_eq!(search.eliminate_pure_literals(&[]), 3);
+        assert!(search.reconstruction.is_eliminated(a.var()));
+        assert_eq!(search.clause_store.iter().count(), 0);
+        assert_eq!(search.trail.assignment().size(), 0);
+        assert!(instance.solve().model().is_some());
+
+        // Nothing was fixed, so a can still be false
+        let solution = instance.solve_with_assumptions(&[a.invert()]);
+        assert_eq!(solution.model().unwrap().value(b.var()), Some(true));
+        instance.add_clause(&[a.invert()]);
+        let solution = instance.solve();
+        assert_eq!(solution.model().unwrap().value(a.var()), Some(false));
+    }
+
+    #[test]
+    fn test_add_empty_clause() {
+        let mut vr = VariableRegister::new();
+        let a = Literal::new(vr.create_original("a"), true);
+        let instance = Instance::new_from_clauses(vec![Clause::new(&[a])], vr);
+
+        let mut unpreprocessed = instance.clone();
+        unpreprocessed.set_preprocessing(false);
+        for mut instance in [instance, unpreprocessed] {
+            instance.add_clause(&[]);
+            assert_eq!(instance.count_models().unwrap(), 0u32.into());
+            assert!(instance.solve().model().is_none());
+        }
+    }
+
+    #[test]
+    fn test_solve_invalid_model() {
+        let mut vr = VariableRegister::new();
+        let a = Literal::new(vr.create_original("a"), true);
+        let mut instance = Instance::new_from_clauses(vec![Clause::new(&[a])], vr);
+        instance.set_verify_models(true);
+        assert!(instance.solve().model().is_some());
+
+        // A clause the search never sees, so the model it finds cannot satisfy it
+        instance.clauses.push(Clause::new_with_id(1, &[a.invert()]));
+        let solution = instance.solve();
+        assert!(solution.model().is_none());
+        match solution.result {
+            SolveResult::InvalidModel { model, error } => {
+                assert_eq!(model.literals(), vec![a]);
+                assert_eq!(
+                    error,
+                    ModelError::UnsatisfiedClause {
+                        clause: 1,
+                        literals: vec![a.invert()]
+                    }
+                );
+            }
+            result => panic!("expected an invalid model, got {:?}", result),
+        }
+    }
+
+    #[test]
+    fn test_incremental_add_clauses() {
+        let mut vr = VariableRegister::new();
+        let a = Literal::new(vr.create_original("a"), true);
+        let b = Literal::new(vr.create_original("b"), true);
+        let mut instance = Instance::new_from_clauses(vec![Clause::new(&[a, b])], vr);
+        assert!(instance.solve().model().is_some());
+
+        instance.add_clause(&[a.invert()]);
+        let solution = instance.solve();
+        let expected = LiteralSet::from_assignment_vec(&vec![a.invert(), b]);
+        assert_eq!(solution.model().map(|m| &m.assignment), Some(&expected));
+
+        // New variables can be brought in along the way
+        let c = Literal::new(instance.add_variable("c"), true);
+        instance.add_clause(&[b.invert(), c]);
+        let solution = instance.solve();
+        assert_eq!(solution.model().unwrap().value(c.var()), Some(true));
+
+        instance.add_clause(&[c.invert()]);
+        assert!(instance.solve().model().is_none());
+        // Once unsatisfiable, always unsatisfiable
+        instance.add_clause(&[a]);
+        assert!(instance.solve().model().is_none());
+    }
+
+    #[test]
+    fn test_solve_with_assumptions() {
+        let mut vr = VariableRegister::new();
+        let a = Literal::new(vr.create_original("a"), true);
+        let b = Literal::new(vr.create_original("b"), true);
+        let c = Literal::new(vr.create_original("c"), true);
+        let clauses = vec![Clause::new(&[a, b]), Clause::new(&[a.invert(), c])];
+        let mut instance = Instance::new_from_clauses(clauses, vr);
+
+        let solution = instance.solve_with_assumptions(&[c.invert()]);
+        let expected = LiteralSet::from_assignment_vec(&vec![a.invert(), b, c.invert()]);
+        assert_eq!(solution.model().map(|m| &m.assignment), Some(&expected));
+
+        assert!(instance
+            .solve_with_assumptions(&[b.invert(), c.invert()])
+            .model()
+            .is_none());
+        // The assumptions do not outlive the call
+        assert!(instance.solve().model().is_some());
+        assert!(instance
+            .solve_with_assumptions(&[b.invert()])
+            .model()
+            .is_some());
+    }
+
+    #[test]
+    fn test_failed_assumptions() {
+        let mut vr = VariableRegister::new();
+        let a = Literal::new(vr.create_original("a"), true);
+        let b = Literal::new(vr.create_original("b"), true);
+        let c = Literal::new(vr.create_original("c"), true);
+        let d = Literal::new(vr.create_original("d"), true);
+        let clauses = vec![
+            Clause::new(&[a.invert(), b.invert()]),
+            Clause::new(&[b.invert(), c, d]),
+            Clause::new(&[d.invert()]),
+        ];
+        let mut instance = Instance::new_from_clauses(clauses, vr);
+
+        // c is irrelevant to the conflict between a and b
+        let solution = instance.solve_with_assumptions(&[c, a, b]);
+        assert!(solution.model().is_none());
+        assert_eq!(solution.failed_assumptions(), &[a, b]);
+
+        // b=true forces c=true, as d is false at the root
+        let solution = instance.solve_with_assumptions(&[b, c.invert()]);
+        assert_eq!(solution.failed_assumptions(), &[b, c.invert()]);
+
+        let solution = instance.solve_with_assumptions(&[c, d]);
+        assert_eq!(solution.failed_assumptions(), &[d]);
+
+        let solution = instance.solve_with_assumptions(&[a, c]);
+        assert!(solution.model().is_some());
+        assert!(solution.failed_assumptions().is_empty());
+
+        instance.add_clause(&[b]);
+        instance.add_clause(&[c.invert()]);
+        let solution = instance.solve_with_assumptions(&[a]);
+        assert!(solution.model().is_none());
+        assert!(solution.failed_assumptions().is_empty());
+    }
+
+    // Preprocessing eliminates b, which later clauses and assumptions bring back
+    #[test]
+    fn test_incremental_after_preprocessing() {
+        let mut vr = VariableRegister::new();
+        let a = Literal::new(vr.create_original("a"), true);
+        let b = Literal::new(vr.create_original("b"), true);
+        let c = Literal::new(vr.create_original("c"), true);
+        let clauses = vec![Clause::new(&[a, b]), Clause::new(&[b.invert(), c])];
+        let mut instance = Instance::new_from_clauses(clauses, vr);
+
+        assert!(instance.solve().model().is_some());
+        let solution = instance.solve_with_assumptions(&[a.invert(), c.invert()]);
+        assert!(solution.model().is_none());
+        let solution = instance.solve_with_assumptions(&[b, c.invert()]);
+        assert_eq!(solution.failed_assumptions(), &[b, c.invert()]);
+
+        instance.add_clause(&[b.invert()]);
+        let solution = instance.solve();
+        let model = solution.model().unwrap();
+        assert_eq!(model.value(a.var()), Some(true));
+        assert_eq!(model.value(b.var()), Some(false));
+        instance.add_clause(&[a.invert()]);
+        assert!(instance.solve().model().is_none());
+    }
+
+    #[test]
+    fn test_learnt_clauses_persist() {
+        let mut instance =
+            dimacs::parse("examples/problem_specs/sat/aim-50-1_6-yes1-4.cnf").unwrap();
+        // Preprocessed, the problem is easy enough to need no learnt clauses
+        instance.set_preprocessing(false);
+        let first = instance.solve();
+        assert!(first.stats.learnt_clause_count > 0);
+        // Everything we needed to find the solution was learnt the first time around
+        let second = instance.solve();
+        assert_eq!(first.result, second.result);
+        assert_eq!(second.stats.backtrack_count, 0);
+    }
+
+    #[test]
+    fn test_limits() {
+        let mut instance = dimacs::parse("examples/problem_specs/unsat/dubois20.cnf").unwrap();
+        instance.set_preprocessing(false);
+        instance.set_limits(Limits {
+            conflicts: Some(5),
+            ..Limits::default()
+        });
+        let sol = instance.solve();
+        assert_eq!(sol.result, SolveResult::Unknown(Limit::Conflicts));
+        assert_eq!(sol.assignments(), None);
+        assert_eq!(sol.stats.backtrack_count, 5);
+
+        // Lifting the limit picks up where we left off
+        instance.set_limits(Limits::default());
+        assert_eq!(instance.solve().result, SolveResult::Unsat);
+    }
+
+    #[test]
+    fn test_interrupt() {
+        let interrupt = Arc::new(AtomicBool::new(true));
+        let mut instance = dimacs::parse("examples/problem_specs/unsat/dubois20.cnf").unwrap();
+        instance.set_preprocessing(false);
+        instance.set_limits(Limits {
+            interrupt: Some(interrupt.clone()),
+            ..Limits::default()
+        });
+        assert_eq!(
+            instance.solve().result,
+            SolveResult::Unknown(Limit::Interrupted)
+        );
+
+        interrupt.store(false, Ordering::Relaxed);
+        assert_eq!(instance.solve().result, SolveResult::Unsat);
     }
 }
@@ -0,0 +1,198 @@
+use crate::instance::*;
+
+use super::dfs::{Instance, SolveResult};
+use super::limits::Limit;
+use super::model::Model;
+use super::verify::ModelError;
+
+/// Iterates over the models of an instance, blocking each one found before looking for the
+/// next. The blocking clauses are guarded by a selector variable that is assumed true while
+/// enumerating and fixed false once the iterator is dropped, so the instance gets back all its
+/// models afterwards while keeping what it learnt. They only go into the search, not the
+/// instance's clauses, so all that is left of an enumeration is its selector variable.
+pub struct Enumerate<'a> {
+    instance: &'a mut Instance,
+    selector: Literal,
+    projection: Option<Vec<Variable>>,
+    max_count: Option<usize>,
+    count: usize,
+    done: bool,
+    stopped_by: Option<Limit>,
+    invalid_model: Option<ModelError>,
+}
+
+impl<'a> Enumerate<'a> {
+    pub(crate) fn new(instance: &'a mut Instance) -> Enumerate<'a> {
+        let selector = Literal::new(instance.add_tseitin_variable(), true);
+        Enumerate {
+            instance,
+            selector,
+            projection: None,
+            max_count: None,
+            count: 0,
+            done: false,
+            stopped_by: None,
+            invalid_model: None,
+        }
+    }
+
+    /// Only yields models that differ on these variables, one per distinct assignment of them.
+    /// Defaults to the original variables of the instance.
+    pub fn project(mut self, variables: impl IntoIterator<Item = Variable>) -> Self {
+        self.projection = Some(variables.into_iter().collect());
+        self
+    }
+
+    /// Stops after this many models
+    pub fn max_count(mut self, max_count: usize) -> Self {
+        self.max_count = Some(max_count);
+        self
+    }
+
+    /// The limit that ended the enumeration early, if any. When None once the iterator is
+    /// exhausted, every model was found (or the maximum count was reached).
+    pub fn stopped_by(&self) -> Option<Limit> {
+        self.stopped_by
+    }
+
+    /// Why the last model found did not satisfy the instance, if model verification caught
+    /// one. The enumeration stops there, without yielding it.
+    pub fn invalid_model(&self) -> Option<&ModelError> {
+        self.invalid_model.as_ref()
+    }
+
+    /// The clause ruling out every model that agrees with this one on the projection
+    fn blocking_clause(&self, model: &Model) -> Vec<Literal> {
+        let projected = match &self.projection {
+            Some(variables) => variables
+                .iter()
+                .filter_map(|&var| model.value(var).map(|value| Literal::new(var, !value)))
+                .collect::<Vec<_>>(),
+            None => model.iter().map(|lit| lit.invert()).collect(),
+        };
+        let mut clause = vec![self.selector.invert()];
+        clause.extend(projected);
+        clause.sort();
+        clause.dedup();
+        clause
+    }
+}
+
+impl Iterator for Enumerate<'_> {
+    type Item = Model;
+
+    fn next(&mut self) -> Option<Model> {
+        if self.done || self.max_count.is_some_and(|max| self.count >= max) {
+            return None;
+        }
+        match self
+            .instance
+            .solve_with_assumptions(&[self.selector])
+            .result
+        {
+            SolveResult::Sat(model) => {
+                let blocking_clause = self.blocking_clause(&model);
+                self.instance.add_search_clause(&blocking_clause);
+                self.count += 1;
+                Some(model)
+            }
+            SolveResult::Unsat => {
+                self.done = true;
+                None
+            }
+            SolveResult::Unknown(limit) => {
+                self.done = true;
+                self.stopped_by = Some(limit);
+                None
+            }
+            SolveResult::InvalidModel { error, .. } => {
+                self.done = true;
+                self.invalid_model = Some(error);
+                None
+            }
+        }
+    }
+}
+
+impl Drop for Enumerate<'_> {
+    fn drop(&mut self) {
+        self.instance.add_search_clause(&[self.selector.invert()]);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashSet;
+
+    use crate::{
+        instance::*,
+        solver::{Instance, Limit, Limits, ModelError},
+        variable_registry::VariableRegister,
+    };
+
+    fn instance() -> (Instance, Literal, Literal, Literal) {
+        let mut vr = VariableRegister::new();
+        let a = Literal::new(vr.create_original("a"), true);
+        let b = Literal::new(vr.create_original("b"), true);
+        let c = Literal::new(vr.create_original("c"), true);
+        // a || b, with c free
+        let clauses = vec![Clause::new(&[a, b])];
+        (Instance::new_from_clauses(clauses, vr), a, b, c)
+    }
+
+    #[test]
+    fn test_enumerate() {
+        let (mut instance, a, b, _) = instance();
+        let models = instance.enumerate().collect::<Vec<_>>();
+        assert_eq!(models.len(), 6);
+        let distinct = models
+            .iter()
+            .map(|model| model.iter().collect::<Vec<_>>())
+            .collect::<HashSet<_>>();
+        assert_eq!(distinct.len(), 6);
+        assert!(models
+            .iter()
+            .all(|m| m.value(a.var()) == Some(true) || m.value(b.var()) == Some(true)));
+
+        // Projected onto a, b, so the free c no longer doubles the count
+        assert_eq!(instance.enumerate().project([a.var(), b.var()]).count(), 3);
+        assert_eq!(instance.enumerate().project([a.var()]).count(), 2);
+        assert_eq!(instance.enumerate().max_count(4).count(), 4);
+
+        // Enumerating leaves every model available to the instance, and its clauses as they
+        // were
+        assert_eq!(instance.clauses.len(), 1);
+        instance.add_clause(&[a.invert()]);
+        assert_eq!(instance.enumerate().count(), 2);
+        assert!(instance.solve().model().is_some());
+    }
+
+    #[test]
+    fn test_enumerate_stopped_by_limit() {
+        let (mut instance, _, _, _) = instance();
+        instance.set_limits(Limits {
+            decisions: Some(0),
+            ..Limits::default()
+        });
+        let mut models = instance.enumerate();
+        assert!(models.next().is_none());
+        assert_eq!(models.stopped_by(), Some(Limit::Decisions));
+    }
+
+    #[test]
+    fn test_enumerate_stopped_by_invalid_model() {
+        let (mut instance, a, _, _) = instance();
+        instance.set_verify_models(true);
+        assert!(instance.solve().model().is_some());
+        // Clauses the search never sees, so no model it finds satisfies both
+        instance.clauses.push(Clause::new_with_id(1, &[a.invert()]));
+        instance.clauses.push(Clause::new_with_id(2, &[a]));
+        let mut models = instance.enumerate();
+        assert!(models.next().is_none());
+        assert!(matches!(
+            models.invalid_model(),
+            Some(ModelError::UnsatisfiedClause { .. })
+        ));
+        assert_eq!(models.stopped_by(), None);
+    }
+}
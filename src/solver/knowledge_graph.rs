@@ -1,3 +1,4 @@
+use fnv::{FnvHashMap, FnvHashSet};
 use log::trace;
 
 use crate::instance::*;
@@ -25,6 +26,19 @@ impl KnowledgeGraph {
         }
     }
 
+    pub(crate) fn add_variable(&mut self, var: Variable) {
+        assert_eq!(var.idx(), self.vertices.len());
+        self.vertices.push(Node {
+            trigger: None,
+            decision: None,
+            clause: None,
+        });
+    }
+
+    pub(crate) fn variable_count(&self) -> usize {
+        self.vertices.len()
+    }
+
     pub(crate) fn add_initial(&mut self, decision: Literal) {
         trace!("initial: {:?}", decision);
         self.add_decision(decision);
@@ -65,6 +79,22 @@ impl KnowledgeGraph {
         &self.vertices[var.idx()]
     }
 
+    /// The clauses currently acting as the reason for an inference. These must not be deleted
+    pub(crate) fn reasons(&self) -> FnvHashSet<ClauseRef> {
+        self.vertices.iter().filter_map(|v| v.clause).collect()
+    }
+
+    /// Updates the reasons after the clause store has moved clauses around
+    pub(crate) fn remap_clauses(&mut self, moved: &FnvHashMap<ClauseRef, ClauseRef>) {
+        for v in self.vertices.iter_mut() {
+            if let Some(clause) = v.clause {
+                if let Some(&new) = moved.get(&clause) {
+                    v.clause = Some(new);
+                }
+            }
+        }
+    }
+
     #[allow(dead_code)]
     pub(crate) fn as_dot(&self, store: &ClauseStore, trail: &Trail) -> String {
         let mut lines = vec!["digraph knowledge_graph {".to_owned()];
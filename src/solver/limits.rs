@@ -0,0 +1,109 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Budgets for each call to solve. Once any of them runs out, the solve gives up and reports
+/// an unknown result. Everything learnt up to that point is kept for the next call.
+#[derive(Debug, Clone, Default)]
+pub struct Limits {
+    pub conflicts: Option<usize>,
+    pub decisions: Option<usize>,
+    pub propagations: Option<usize>,
+    pub time: Option<Duration>,
+    /// Stops the solve once set to true, e.g. from another thread
+    pub interrupt: Option<Arc<AtomicBool>>,
+}
+
+/// The limit that stopped a solve before it found an answer
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Limit {
+    Conflicts,
+    Decisions,
+    Propagations,
+    Time,
+    Interrupted,
+}
+
+/// Tracks a single call to solve against its limits
+pub(crate) struct Budget<'a> {
+    limits: &'a Limits,
+    deadline: Option<Instant>,
+}
+
+impl<'a> Budget<'a> {
+    pub(crate) fn start(limits: &'a Limits) -> Budget<'a> {
+        Budget {
+            limits,
+            deadline: limits.time.map(|time| Instant::now() + time),
+        }
+    }
+
+    /// The first limit that has been used up, if any
+    pub(crate) fn exhausted(
+        &self,
+        conflicts: usize,
+        decisions: usize,
+        propagations: usize,
+    ) -> Option<Limit> {
+        let over = |limit: Option<usize>, used: usize| limit.is_some_and(|limit| used >= limit);
+        if over(self.limits.conflicts, conflicts) {
+            return Some(Limit::Conflicts);
+        }
+        if over(self.limits.decisions, decisions) {
+            return Some(Limit::Decisions);
+        }
+        if over(self.limits.propagations, propagations) {
+            return Some(Limit::Propagations);
+        }
+        if let Some(interrupt) = &self.limits.interrupt {
+            if interrupt.load(Ordering::Relaxed) {
+                return Some(Limit::Interrupted);
+            }
+        }
+        if let Some(deadline) = self.deadline {
+            if Instant::now() >= deadline {
+                return Some(Limit::Time);
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use crate::solver::limits::{Budget, Limit, Limits};
+
+    #[test]
+    fn test_budget_exhausted() {
+        let unlimited = Limits::default();
+        assert_eq!(
+            Budget::start(&unlimited).exhausted(usize::MAX, usize::MAX, usize::MAX),
+            None
+        );
+
+        let limits = Limits {
+            conflicts: Some(10),
+            propagations: Some(100),
+            ..Limits::default()
+        };
+        let budget = Budget::start(&limits);
+        assert_eq!(budget.exhausted(9, 1000, 99), None);
+        assert_eq!(budget.exhausted(10, 0, 0), Some(Limit::Conflicts));
+        assert_eq!(budget.exhausted(0, 0, 100), Some(Limit::Propagations));
+
+        let interrupt = Arc::new(AtomicBool::new(false));
+        let limits = Limits {
+            interrupt: Some(interrupt.clone()),
+            time: Some(Duration::ZERO),
+            ..Limits::default()
+        };
+        let budget = Budget::start(&limits);
+        assert_eq!(budget.exhausted(0, 0, 0), Some(Limit::Time));
+        interrupt.store(true, Ordering::Relaxed);
+        assert_eq!(budget.exhausted(0, 0, 0), Some(Limit::Interrupted));
+    }
+}
@@ -1,11 +1,62 @@
 mod assignment_set;
 mod backtrack;
-mod clause_index;
 mod clause_store;
+mod config;
+mod count;
+mod decision;
+mod enumerate;
 mod knowledge_graph;
+mod limits;
+mod model;
+mod phase;
+mod portfolio;
+mod preprocess;
+mod probe;
+mod proof;
+mod reduce;
+mod restart;
+mod rng;
+mod root;
 mod sorted_vec;
+mod subsume;
 mod trail;
 mod unit_propagator;
+mod verify;
+mod vivify;
+mod watches;
 
 mod dfs;
+pub use crate::solver::backtrack::{AnalyzedConflict, BacktrackStrategy, Backtracking, Conflict};
+pub use crate::solver::config::SolverConfig;
+pub use crate::solver::decision::{DecisionStrategy, Decisions};
 pub use crate::solver::dfs::*;
+pub use crate::solver::enumerate::Enumerate;
+pub use crate::solver::limits::{Limit, Limits};
+pub use crate::solver::model::Model;
+pub use crate::solver::phase::InitialPhase;
+pub use crate::solver::portfolio::{Portfolio, WorkerConfig};
+pub use crate::solver::proof::ProofFormat;
+pub use crate::solver::reduce::{ClauseDeletion, ClauseDeletionPolicy, LearntClause};
+pub use crate::solver::restart::{RestartPolicy, Restarts};
+pub use crate::solver::trail::{Trail, TrailEntry};
+pub use crate::solver::verify::ModelError;
+pub use num_bigint::BigUint;
+
+#[cfg(test)]
+mod test {
+    use crate::instance::*;
+
+    /// The first variables, as positive literals
+    pub(crate) fn literals<const N: usize>() -> [Literal; N] {
+        std::array::from_fn(|ix| Literal::new(Variable(ix as u64), true))
+    }
+
+    /// The clauses, numbered in order
+    pub(crate) fn clauses(clauses: &[Vec<Literal>]) -> Vec<Clause> {
+        clauses
+            .iter()
+            .enumerate()
+            .map(|(ix, literals)| Clause::new_with_id(ix, literals))
+            .collect()
+    }
+}
@@ -0,0 +1,123 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+
+use crate::instance::*;
+use crate::variable_registry::VariableRegister;
+
+use super::assignment_set::LiteralSet;
+
+/// A satisfying assignment, along with the names of the variables it assigns
+#[derive(Clone)]
+pub struct Model {
+    variables: Arc<VariableRegister>,
+    pub(crate) assignment: LiteralSet,
+}
+
+impl Model {
+    pub(crate) fn new(variables: Arc<VariableRegister>, assignment: LiteralSet) -> Model {
+        Model {
+            variables,
+            assignment,
+        }
+    }
+
+    /// The value of the variable, if the model assigns it
+    pub fn value(&self, var: Variable) -> Option<bool> {
+        self.assignment.get(var).map(|lit| lit.polarity())
+    }
+
+    /// The value of the variable registered under the name
+    pub fn value_by_name(&self, name: &str) -> Option<bool> {
+        self.value(self.variables.get_by_name(name)?)
+    }
+
+    /// The assigned literal of each original variable, in the order they were created. The
+    /// variables `ProblemBuilder` introduces for its own use are skipped.
+    pub fn iter(&self) -> impl Iterator<Item = Literal> + '_ {
+        self.variables
+            .iter_original()
+            .filter_map(|var| self.assignment.get(var))
+    }
+
+    /// Every assigned literal, including those of variables `ProblemBuilder` introduced
+    pub fn literals(&self) -> Vec<Literal> {
+        self.assignment.as_assignment_vec()
+    }
+
+    /// The value of each original variable, by name
+    pub fn to_hash_map(&self) -> HashMap<String, bool> {
+        self.iter()
+            .map(|lit| (self.variables.get(lit.var()).to_string(), lit.polarity()))
+            .collect()
+    }
+}
+
+/// Models are equal if they assign the same values
+impl PartialEq for Model {
+    fn eq(&self, other: &Self) -> bool {
+        self.assignment == other.assignment
+    }
+}
+
+impl fmt::Debug for Model {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut first = true;
+        for var in self.variables.iter_original() {
+            if !first {
+                write!(f, ", ")?;
+            }
+            first = false;
+
+            let formatted_val = match self.value(var) {
+                Some(val) => format!("{:?}", val),
+                None => "undef".to_string(),
+            };
+            write!(f, "{}={}", self.variables.get(var), formatted_val)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+    use std::sync::Arc;
+
+    use crate::{
+        instance::*,
+        solver::{assignment_set::LiteralSet, model::Model},
+        variable_registry::VariableRegister,
+    };
+
+    #[test]
+    fn test_model_accessors() {
+        let mut vars = VariableRegister::new();
+        let a = vars.create_original("a");
+        let t = vars.create_tseitin();
+        let b = vars.create_original("b");
+        let c = vars.create_original("c");
+        let model = Model::new(
+            Arc::new(vars),
+            LiteralSet::from_assignment_vec(&vec![
+                Literal::new(a, true),
+                Literal::new(t, false),
+                Literal::new(b, false),
+            ]),
+        );
+
+        assert_eq!(model.value(a), Some(true));
+        assert_eq!(model.value(c), None);
+        assert_eq!(model.value_by_name("b"), Some(false));
+        assert_eq!(model.value_by_name("d"), None);
+        assert_eq!(
+            model.iter().collect::<Vec<_>>(),
+            vec![Literal::new(a, true), Literal::new(b, false)]
+        );
+        assert_eq!(
+            model.to_hash_map(),
+            HashMap::from([("a".to_string(), true), ("b".to_string(), false)])
+        );
+        assert_eq!(model.literals().len(), 3);
+    }
+}
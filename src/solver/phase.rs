@@ -0,0 +1,106 @@
+use std::collections::HashMap;
+
+use crate::instance::*;
+
+use super::rng::Rng;
+
+/// The polarity the solver branches with on a variable it has not assigned before. Once a
+/// variable has been assigned, the solver reuses whichever polarity it last had instead.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum InitialPhase {
+    #[default]
+    True,
+    False,
+    /// Pick each variable's polarity at random, reproducibly for a given seed
+    Random {
+        seed: u64,
+    },
+    /// Use the given polarity for each variable. Variables not in the map start out true
+    PerVariable(HashMap<Variable, bool>),
+}
+
+/// Remembers the polarity each variable was last assigned (phase saving), so that after a
+/// backtrack we branch back into the same part of the search space.
+#[derive(Debug, Clone)]
+pub(crate) struct SavedPhases {
+    // Indexed by Variable::idx
+    phases: Vec<bool>,
+    // Only used for InitialPhase::Random
+    rng: Option<Rng>,
+}
+
+impl SavedPhases {
+    pub(crate) fn new(variable_count: usize, initial: &InitialPhase) -> SavedPhases {
+        let rng = match initial {
+            InitialPhase::Random { seed } => Some(Rng::new(*seed)),
+            _ => None,
+        };
+        let mut phases = SavedPhases {
+            phases: Vec::with_capacity(variable_count),
+            rng,
+        };
+        for ix in 0..variable_count {
+            phases.add_variable(Variable(ix as u64), initial);
+        }
+        phases
+    }
+
+    pub(crate) fn add_variable(&mut self, var: Variable, initial: &InitialPhase) {
+        assert_eq!(var.idx(), self.phases.len());
+        let phase = match initial {
+            InitialPhase::True => true,
+            InitialPhase::False => false,
+            InitialPhase::Random { .. } => self.rng.as_mut().unwrap().next_bool(),
+            InitialPhase::PerVariable(phases) => *phases.get(&var).unwrap_or(&true),
+        };
+        self.phases.push(phase);
+    }
+
+    /// The literal to branch on for the variable
+    pub(crate) fn decide(&self, var: Variable) -> Literal {
+        Literal::new(var, self.phases[var.idx()])
+    }
+
+    /// Records the polarity of assignments as they are undone
+    pub(crate) fn save(&mut self, literals: &[Literal]) {
+        for lit in literals {
+            self.phases[lit.var().idx()] = lit.polarity();
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use crate::instance::*;
+
+    use super::{InitialPhase, SavedPhases};
+
+    #[test]
+    fn test_initial_phases() {
+        let a = Variable(0);
+        let b = Variable(1);
+
+        let phases = SavedPhases::new(2, &InitialPhase::False);
+        assert_eq!(phases.decide(a), Literal::new(a, false));
+
+        let phases = SavedPhases::new(2, &InitialPhase::PerVariable(HashMap::from([(b, false)])));
+        assert_eq!(phases.decide(a), Literal::new(a, true));
+        assert_eq!(phases.decide(b), Literal::new(b, false));
+
+        let random = InitialPhase::Random { seed: 42 };
+        let xs = SavedPhases::new(64, &random);
+        let ys = SavedPhases::new(64, &random);
+        assert_eq!(xs.phases, ys.phases);
+        assert!(xs.phases.iter().any(|&p| p) && xs.phases.iter().any(|&p| !p));
+    }
+
+    #[test]
+    fn test_saves_last_polarity() {
+        let a = Variable(0);
+        let mut phases = SavedPhases::new(1, &InitialPhase::True);
+        phases.save(&[Literal::new(a, false)]);
+        assert_eq!(phases.decide(a), Literal::new(a, false));
+    }
+}
@@ -0,0 +1,235 @@
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+
+use crate::instance::*;
+
+use super::dfs::{Instance, Solution, SolveResult};
+use super::phase::InitialPhase;
+use super::restart::Restarts;
+
+/// How one of the solvers in a portfolio searches
+#[derive(Debug, Clone, Default)]
+pub struct WorkerConfig {
+    pub initial_phase: InitialPhase,
+    pub restarts: Restarts,
+}
+
+/// A set of differently configured solvers, which race each other on their own threads. The
+/// first to find an answer stops the rest. Along the way, they share the short clauses they
+/// learn with each other.
+#[derive(Debug, Clone)]
+pub struct Portfolio {
+    workers: Vec<WorkerConfig>,
+    max_shared_clause_len: usize,
+}
+
+impl Portfolio {
+    pub fn new(workers: Vec<WorkerConfig>) -> Portfolio {
+        assert!(!workers.is_empty(), "a portfolio needs at least one worker");
+        Portfolio {
+            workers,
+            max_shared_clause_len: 8,
+        }
+    }
+
+    /// A portfolio of `n` workers, varying their phases (including random ones with different
+    /// seeds) and restart policies
+    pub fn diverse(n: usize) -> Portfolio {
+        let workers = (0..n)
+            .map(|ix| WorkerConfig {
+                initial_phase: match ix % 4 {
+                    0 => InitialPhase::True,
+                    1 => InitialPhase::False,
+                    _ => InitialPhase::Random { seed: ix as u64 },
+                },
+                restarts: match ix % 2 {
+                    0 => Restarts::Glucose,
+                    _ => Restarts::Luby { unit: 100 },
+                },
+            })
+            .collect();
+        Portfolio::new(workers)
+    }
+
+    /// Only learnt clauses of up to this many literals are shared. Zero turns sharing off.
+    pub fn share_clauses_up_to(mut self, len: usize) -> Portfolio {
+        self.max_shared_clause_len = len;
+        self
+    }
+
+    pub(crate) fn solve(&self, instance: &Instance) -> Solution {
+        let pool = ClausePool::new(self.workers.len());
+        let winner = AtomicUsize::new(usize::MAX);
+        let mut solutions = thread::scope(|scope| {
+            let handles = self
+                .workers
+                .iter()
+                .enumerate()
+                .map(|(ix, config)| {
+                    let mut worker = instance.clone();
+                    worker.set_initial_phase(config.initial_phase.clone());
+                    worker.set_restarts(config.restarts);
+                    let (pool, winner) = (&pool, &winner);
+                    scope.spawn(move || {
+                        let mut sharing = ClauseSharing::new(ix, pool, self.max_shared_clause_len);
+                        let solution = worker.solve_sharing(&[], Some(&mut sharing));
+                        if !matches!(solution.result, SolveResult::Unknown(_)) {
+                            let _ = winner.compare_exchange(
+                                usize::MAX,
+                                ix,
+                                Ordering::SeqCst,
+                                Ordering::SeqCst,
+                            );
+                            pool.stop.store(true, Ordering::Relaxed);
+                        }
+                        solution
+                    })
+                })
+                .collect::<Vec<_>>();
+            handles
+                .into_iter()
+                .map(|handle| handle.join().unwrap())
+                .collect::<Vec<_>>()
+        });
+        // Without a winner, every worker ran out of its budget
+        let winner = match winner.into_inner() {
+            usize::MAX => 0,
+            ix => ix,
+        };
+        solutions.swap_remove(winner)
+    }
+}
+
+/// The clauses shared by the workers, and the flag that stops them. Each worker has an inbox
+/// of the clauses the others learnt since it last looked, which it empties when it does, so
+/// the pool only holds what has yet to be imported.
+struct ClausePool {
+    inboxes: Vec<Mutex<Vec<Vec<Literal>>>>,
+    stop: AtomicBool,
+}
+
+impl ClausePool {
+    fn new(workers: usize) -> ClausePool {
+        ClausePool {
+            inboxes: (0..workers).map(|_| Mutex::default()).collect(),
+            stop: AtomicBool::new(false),
+        }
+    }
+}
+
+/// A worker's view of the pool
+pub(crate) struct ClauseSharing<'a> {
+    worker: usize,
+    pool: &'a ClausePool,
+    max_len: usize,
+}
+
+impl<'a> ClauseSharing<'a> {
+    fn new(worker: usize, pool: &'a ClausePool, max_len: usize) -> ClauseSharing<'a> {
+        ClauseSharing {
+            worker,
+            pool,
+            max_len,
+        }
+    }
+
+    /// Whether another worker has found the answer
+    pub(crate) fn stopped(&self) -> bool {
+        self.pool.stop.load(Ordering::Relaxed)
+    }
+
+    /// Offers a clause we learnt to the other workers
+    pub(crate) fn export(&self, clause: &[Literal]) {
+        if clause.len() > self.max_len {
+            return;
+        }
+        for (worker, inbox) in self.pool.inboxes.iter().enumerate() {
+            if worker != self.worker {
+                let clause = clause.to_vec();
+                inbox.lock().unwrap().push(clause);
+            }
+        }
+    }
+
+    /// The clauses the other workers have learnt since we last looked
+    pub(crate) fn import(&mut self) -> Vec<Vec<Literal>> {
+        std::mem::take(&mut *self.pool.inboxes[self.worker].lock().unwrap())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use crate::{
+        dimacs,
+        solver::{
+            portfolio::{ClausePool, ClauseSharing, Portfolio},
+            Instance, Limit, Limits, SolveResult,
+        },
+        *,
+    };
+
+    #[test]
+    fn test_instance_is_send() {
+        fn assert_send<T: Send>() {}
+        assert_send::<Instance>();
+    }
+
+    #[test]
+    fn test_clause_sharing() {
+        let pool = ClausePool::new(2);
+        let mut first = ClauseSharing::new(0, &pool, 2);
+        let mut second = ClauseSharing::new(1, &pool, 2);
+        let (a, b, c) = (
+            Literal::new(Variable(0), true),
+            Literal::new(Variable(1), true),
+            Literal::new(Variable(2), true),
+        );
+
+        first.export(&[a, b]);
+        first.export(&[a, b, c]);
+        second.export(&[c]);
+        assert_eq!(first.import(), vec![vec![c]]);
+        assert_eq!(second.import(), vec![vec![a, b]]);
+        assert!(second.import().is_empty());
+        // Nothing is kept once every worker has imported it
+        assert!(pool
+            .inboxes
+            .iter()
+            .all(|inbox| inbox.lock().unwrap().is_empty()));
+    }
+
+    #[test]
+    fn test_solve_portfolio() {
+        for portfolio in [
+            Portfolio::diverse(4),
+            Portfolio::diverse(3).share_clauses_up_to(0),
+        ] {
+            let instance =
+                dimacs::parse("examples/problem_specs/sat/aim-50-1_6-yes1-4.cnf").unwrap();
+            assert!(instance.solve_portfolio(&portfolio).model().is_some());
+
+            let instance = dimacs::parse("examples/problem_specs/unsat/dubois20.cnf").unwrap();
+            assert_eq!(
+                instance.solve_portfolio(&portfolio).result,
+                SolveResult::Unsat
+            );
+        }
+    }
+
+    #[test]
+    fn test_portfolio_limits() {
+        let mut instance = dimacs::parse("examples/problem_specs/unsat/dubois20.cnf").unwrap();
+        instance.set_preprocessing(false);
+        instance.set_limits(Limits {
+            interrupt: Some(Arc::new(true.into())),
+            ..Limits::default()
+        });
+        assert_eq!(
+            instance.solve_portfolio(&Portfolio::diverse(2)).result,
+            SolveResult::Unknown(Limit::Interrupted)
+        );
+    }
+}
@@ -0,0 +1,117 @@
+use std::collections::VecDeque;
+
+use crate::instance::*;
+
+use super::{Preprocessor, PASS_BUDGET};
+
+impl Preprocessor {
+    /// Blocked clause elimination. A clause is blocked on one of its literals when resolving
+    /// it on that literal with any other clause gives a tautology. Removing it cannot make the
+    /// clauses satisfiable where they were not, and flipping the literal repairs any model of
+    /// what is left that does not satisfy it.
+    pub(super) fn eliminate_blocked_clauses(&mut self) {
+        let mut budget = PASS_BUDGET;
+        let mut queued = vec![true; self.clauses.len()];
+        let mut queue = (0..self.clauses.len())
+            .filter(|&ix| !self.removed[ix])
+            .collect::<VecDeque<_>>();
+
+        let mut eliminated = 0;
+        while let Some(ix) = queue.pop_front() {
+            queued[ix] = false;
+            if budget == 0 {
+                break;
+            }
+            if self.removed[ix] {
+                continue;
+            }
+            let literals = self.clauses[ix].clone();
+            let Some(blocking) = literals
+                .iter()
+                .copied()
+                .find(|&lit| self.is_blocked_on(&literals, lit, &mut budget))
+            else {
+                continue;
+            };
+
+            eliminated += 1;
+            self.remove_clause(ix, blocking);
+            // Clauses that resolved with this one to a non-tautology may be blocked now
+            for lit in literals {
+                for &other in self.occurrences(lit.invert()) {
+                    if !queued[other] {
+                        queued[other] = true;
+                        queue.push_back(other);
+                    }
+                }
+            }
+        }
+        log::info!("eliminated {} blocked clauses", eliminated);
+    }
+
+    /// Whether every resolvent of the clause on the literal is a tautology
+    fn is_blocked_on(&mut self, clause: &[Literal], lit: Literal, budget: &mut usize) -> bool {
+        let others = self.occurrences(lit.invert()).to_vec();
+        others.into_iter().all(|other| {
+            let other = &self.clauses[other];
+            *budget = budget.saturating_sub(other.len());
+            other
+                .iter()
+                .any(|&l| l != lit.invert() && clause.contains(&l.invert()))
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        problem_builder::ProblemBuilder,
+        solver::{
+            assignment_set::LiteralSet,
+            preprocess::Preprocessor,
+            test::{clauses, literals},
+        },
+    };
+
+    #[test]
+    fn test_eliminate_blocked_clauses() {
+        let [a, b, c] = literals();
+        // (a || b) is blocked on a, as its only resolvent with (!a || !b || c) is a tautology
+        let clauses = clauses(&[vec![a, b], vec![a.invert(), b.invert(), c], vec![b, c]]);
+        let mut preprocessor = Preprocessor::new(&clauses, 3);
+        preprocessor.eliminate_blocked_clauses();
+        assert!(preprocessor.removed[0]);
+
+        // However the rest went, the model is repaired to satisfy every clause
+        let mut assignment = LiteralSet::from_assignment_vec(&vec![a.invert(), b.invert(), c]);
+        preprocessor.reconstruction.extend(&mut assignment);
+        for clause in clauses.iter() {
+            assert!(clause
+                .literals()
+                .iter()
+                .any(|&lit| assignment.contains(lit)));
+        }
+    }
+
+    #[test]
+    fn test_blocked_tseitin_clauses() {
+        // The clauses saying that x or y implies `or` are not needed, as `or` is only ever
+        // required to be true
+        let mut pb = ProblemBuilder::new();
+        let (x, y) = (pb.var("x"), pb.var("y"));
+        let or = pb.or(x, y);
+        pb.require(or);
+        let instance = pb.build();
+
+        let mut preprocessor = Preprocessor::new(&instance.clauses, instance.variables.count());
+        preprocessor.eliminate_blocked_clauses();
+        assert!(
+            preprocessor
+                .removed
+                .iter()
+                .filter(|&&removed| removed)
+                .count()
+                >= 2
+        );
+    }
+}
@@ -0,0 +1,186 @@
+use std::collections::VecDeque;
+
+use crate::instance::*;
+
+use super::{Preprocessor, PASS_BUDGET};
+
+// Variables occurring this often in both polarities are too costly to try
+const MAX_OCCURRENCES: usize = 16;
+// Resolvents longer than this are not worth adding
+const MAX_RESOLVENT_LEN: usize = 24;
+
+impl Preprocessor {
+    /// Bounded variable elimination, as in SatELite. A variable is eliminated by replacing the
+    /// clauses it occurs in with all their resolvents on it, when that does not add clauses.
+    pub(super) fn eliminate_variables(&mut self) {
+        let mut budget = PASS_BUDGET;
+        let mut queued = vec![true; self.variable_count()];
+        let mut candidates = (0..self.variable_count() as u64)
+            .map(Variable)
+            .collect::<Vec<_>>();
+        // Cheapest first, as eliminating them tends to make the rest cheaper
+        candidates.sort_by_key(|&var| {
+            let pos = self.occurrences[Literal::new(var, true).idx()].len();
+            let neg = self.occurrences[Literal::new(var, false).idx()].len();
+            pos * neg
+        });
+        let mut queue = candidates.into_iter().collect::<VecDeque<_>>();
+
+        let mut eliminated = 0;
+        while let Some(var) = queue.pop_front() {
+            queued[var.idx()] = false;
+            if self.unsatisfiable || budget == 0 {
+                break;
+            }
+            let Some(resolvents) = self.resolvents(var, &mut budget) else {
+                continue;
+            };
+
+            eliminated += 1;
+            let pos = self.occurrences(Literal::new(var, true)).to_vec();
+            let neg = self.occurrences(Literal::new(var, false)).to_vec();
+            for ix in pos {
+                self.remove_clause(ix, Literal::new(var, true));
+            }
+            for ix in neg {
+                self.remove_clause(ix, Literal::new(var, false));
+            }
+            self.reconstruction.eliminate(var);
+            for resolvent in resolvents {
+                // The occurrences of the variables in the resolvent changed, so they may have
+                // become worth eliminating
+                for lit in resolvent.iter() {
+                    if !queued[lit.var().idx()] && !self.reconstruction.is_eliminated(lit.var()) {
+                        queued[lit.var().idx()] = true;
+                        queue.push_back(lit.var());
+                    }
+                }
+                self.add_clause(resolvent);
+            }
+        }
+        log::info!("eliminated {} variables", eliminated);
+    }
+
+    /// The resolvents of the clauses of the variable, if replacing the clauses with them
+    /// would not grow the problem
+    fn resolvents(&mut self, var: Variable, budget: &mut usize) -> Option<Vec<Vec<Literal>>> {
+        let pos = self.occurrences(Literal::new(var, true)).to_vec();
+        let neg = self.occurrences(Literal::new(var, false)).to_vec();
+        if pos.is_empty() && neg.is_empty() {
+            return None;
+        }
+        if pos.len() > MAX_OCCURRENCES && neg.len() > MAX_OCCURRENCES {
+            return None;
+        }
+
+        let limit = pos.len() + neg.len();
+        let mut resolvents = vec![];
+        for &p in pos.iter() {
+            for &n in neg.iter() {
+                *budget = budget.saturating_sub(self.clauses[p].len() + self.clauses[n].len());
+                if let Some(resolvent) = resolve(&self.clauses[p], &self.clauses[n], var) {
+                    if resolvent.len() > MAX_RESOLVENT_LEN {
+                        return None;
+                    }
+                    resolvents.push(resolvent);
+                    if resolvents.len() > limit {
+                        return None;
+                    }
+                }
+            }
+        }
+        Some(resolvents)
+    }
+}
+
+/// Resolves two sorted clauses on the variable, which one contains positively and the other
+/// negatively. None if the resolvent is a tautology.
+pub(super) fn resolve(a: &[Literal], b: &[Literal], var: Variable) -> Option<Vec<Literal>> {
+    let mut resolvent = Vec::with_capacity(a.len() + b.len() - 2);
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() || j < b.len() {
+        let lit = match (a.get(i), b.get(j)) {
+            (Some(&x), Some(&y)) if x.var() == y.var() => {
+                i += 1;
+                j += 1;
+                if x == y {
+                    x
+                } else if x.var() == var {
+                    continue;
+                } else {
+                    return None;
+                }
+            }
+            (Some(&x), Some(&y)) if x < y => {
+                i += 1;
+                x
+            }
+            (Some(_), Some(&y)) => {
+                j += 1;
+                y
+            }
+            (Some(&x), None) => {
+                i += 1;
+                x
+            }
+            (None, Some(&y)) => {
+                j += 1;
+                y
+            }
+            (None, None) => unreachable!(),
+        };
+        if lit.var() != var {
+            resolvent.push(lit);
+        }
+    }
+    Some(resolvent)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        problem_builder::ProblemBuilder,
+        solver::{
+            preprocess::{eliminate::resolve, Preprocessor},
+            test::literals,
+        },
+    };
+
+    #[test]
+    fn test_resolve() {
+        let [a, b, c, d] = literals();
+        assert_eq!(
+            resolve(&[a, b, c], &[a, b.invert(), d], b.var()),
+            Some(vec![a, c, d])
+        );
+        assert_eq!(resolve(&[a, b], &[a.invert(), b.invert()], b.var()), None);
+        assert_eq!(resolve(&[b], &[b.invert()], b.var()), Some(vec![]));
+    }
+
+    #[test]
+    fn test_eliminate_tseitin_variables() {
+        // (x || y) && (y || z) && (x || z), built from Tseitin encoded ors
+        let mut pb = ProblemBuilder::new();
+        let (x, y, z) = (pb.var("x"), pb.var("y"), pb.var("z"));
+        for (a, b) in [(x, y), (y, z), (x, z)] {
+            let or = pb.or(a, b);
+            pb.require(or);
+        }
+        let instance = pb.build();
+        let variable_count = instance.variables.count();
+
+        let (clauses, _) = Preprocessor::new(&instance.clauses, variable_count)
+            .run()
+            .unwrap();
+        // Every auxiliary goes, whether eliminated or left in no clauses by blocked clause
+        // elimination, leaving at most the three binary clauses
+        let originals = instance.variables.iter_original().collect::<Vec<_>>();
+        for clause in clauses.iter() {
+            assert!(clause
+                .literals()
+                .iter()
+                .all(|lit| originals.contains(&lit.var())));
+        }
+        assert!(clauses.len() <= 3);
+    }
+}
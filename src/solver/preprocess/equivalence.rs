@@ -0,0 +1,204 @@
+use crate::instance::*;
+
+use super::Preprocessor;
+
+impl Preprocessor {
+    /// Finds literals that imply each other through the binary clauses, which are strongly
+    /// connected components of the binary implication graph, and replaces each component with
+    /// one of its literals. The variables replaced are eliminated, with their equivalence kept
+    /// for reconstruction. A component containing a literal and its negation makes the clauses
+    /// unsatisfiable.
+    pub(super) fn substitute_equivalences(&mut self) {
+        let variable_count = self.variable_count();
+        // Indexed by Literal::idx: the literals each literal implies
+        let mut implications = vec![vec![]; 2 * variable_count];
+        for (literals, &removed) in self.clauses.iter().zip(self.removed.iter()) {
+            if let (false, &[a, b]) = (removed, &literals[..]) {
+                implications[a.invert().idx()].push(b);
+                implications[b.invert().idx()].push(a);
+            }
+        }
+
+        // Indexed by Variable::idx: the literal to put in place of the positive literal
+        let mut substitutions: Vec<Option<Literal>> = vec![None; variable_count];
+        let components = strongly_connected_components(&implications);
+        // Indexed by Literal::idx
+        let mut component_of = vec![0; 2 * variable_count];
+        for (ix, component) in components.iter().enumerate() {
+            for lit in component {
+                component_of[lit.idx()] = ix;
+            }
+        }
+        for component in components.iter().filter(|component| component.len() > 1) {
+            if let Some(lit) = component
+                .iter()
+                .find(|lit| component_of[lit.invert().idx()] == component_of[lit.idx()])
+            {
+                log::info!("{:?} is equivalent to its negation", lit);
+                self.unsatisfiable = true;
+                return;
+            }
+            // A component's negation is a component too, which gets the negated representative
+            let representative = *component.iter().min_by_key(|lit| lit.var()).unwrap();
+            for &lit in component.iter() {
+                if lit == representative {
+                    continue;
+                }
+                let substitute = match lit.polarity() {
+                    true => representative,
+                    false => representative.invert(),
+                };
+                substitutions[lit.var().idx()] = Some(substitute);
+            }
+        }
+        let substitute = |lit: Literal| match substitutions[lit.var().idx()] {
+            Some(substitute) if lit.polarity() => substitute,
+            Some(substitute) => substitute.invert(),
+            None => lit,
+        };
+
+        let mut substituted = 0;
+        for var in (0..variable_count as u64).map(Variable) {
+            let Some(representative) = substitutions[var.idx()] else {
+                continue;
+            };
+            substituted += 1;
+            for polarity in [true, false] {
+                let lit = Literal::new(var, polarity);
+                for ix in self.occurrences(lit).to_vec() {
+                    let mut literals = self.drop_clause(ix);
+                    for lit in literals.iter_mut() {
+                        *lit = substitute(*lit);
+                    }
+                    literals.sort();
+                    literals.dedup();
+                    // Substituting can make a clause a tautology, as in the binary clauses of
+                    // the component itself
+                    if literals.windows(2).all(|w| w[0].var() != w[1].var()) {
+                        self.add_clause(literals);
+                    }
+                }
+            }
+            // The variable takes the value of its representative
+            let lit = Literal::new(var, true);
+            self.reconstruction
+                .push(lit, vec![representative.invert(), lit]);
+            self.reconstruction
+                .push(lit.invert(), vec![representative, lit.invert()]);
+            self.reconstruction.eliminate(var);
+        }
+        log::info!("substituted {} equivalent variables", substituted);
+    }
+}
+
+/// Tarjan's algorithm over the implication graph, without recursion as the chains of
+/// implications can be long. Each component comes out after every component it implies.
+fn strongly_connected_components(implications: &[Vec<Literal>]) -> Vec<Vec<Literal>> {
+    let literal = |idx: usize| Literal::new(Variable(idx as u64 / 2), idx % 2 == 1);
+    let mut index = vec![usize::MAX; implications.len()];
+    let mut low_link = vec![0; implications.len()];
+    let mut on_stack = vec![false; implications.len()];
+    let mut stack = vec![];
+    let mut next_index = 0;
+    let mut components = vec![];
+
+    for root in 0..implications.len() {
+        if index[root] != usize::MAX {
+            continue;
+        }
+        // Each frame is a node, and how many of its implications we have been through
+        let mut frames = vec![(root, 0)];
+        index[root] = next_index;
+        low_link[root] = next_index;
+        next_index += 1;
+        stack.push(root);
+        on_stack[root] = true;
+
+        while let Some(&mut (node, ref mut next)) = frames.last_mut() {
+            if let Some(implied) = implications[node].get(*next) {
+                *next += 1;
+                let implied = implied.idx();
+                if index[implied] == usize::MAX {
+                    index[implied] = next_index;
+                    low_link[implied] = next_index;
+                    next_index += 1;
+                    stack.push(implied);
+                    on_stack[implied] = true;
+                    frames.push((implied, 0));
+                } else if on_stack[implied] {
+                    low_link[node] = low_link[node].min(index[implied]);
+                }
+                continue;
+            }
+
+            frames.pop();
+            if let Some(&(parent, _)) = frames.last() {
+                low_link[parent] = low_link[parent].min(low_link[node]);
+            }
+            if low_link[node] == index[node] {
+                let mut component = vec![];
+                loop {
+                    let member = stack.pop().unwrap();
+                    on_stack[member] = false;
+                    component.push(literal(member));
+                    if member == node {
+                        break;
+                    }
+                }
+                components.push(component);
+            }
+        }
+    }
+    components
+}
+
+#[cfg(test)]
+mod test {
+    use crate::solver::{preprocess::test::preprocessor, test::literals};
+
+    #[test]
+    fn test_substitute_equivalences() {
+        let [a, b, c, d] = literals();
+        // a -> !b -> c -> a, leaving (a || d) in terms of a, and (b || d) as (!a || d)
+        let mut preprocessor = preprocessor(
+            &[
+                vec![a.invert(), b.invert()],
+                vec![b, c],
+                vec![c.invert(), a],
+                vec![a, d],
+                vec![b, d],
+            ],
+            4,
+        );
+        preprocessor.substitute_equivalences();
+        assert!(!preprocessor.unsatisfiable);
+        assert!(preprocessor.reconstruction.is_eliminated(b.var()));
+        assert!(preprocessor.reconstruction.is_eliminated(c.var()));
+        let mut remaining = preprocessor
+            .clauses
+            .iter()
+            .zip(preprocessor.removed.iter())
+            .filter(|(_, &removed)| !removed)
+            .map(|(literals, _)| literals.clone())
+            .collect::<Vec<_>>();
+        remaining.sort();
+        assert_eq!(remaining, vec![vec![a.invert(), d], vec![a, d]]);
+    }
+
+    #[test]
+    fn test_literal_equivalent_to_negation() {
+        let [a, b] = literals();
+        // a <-> b, and a <-> !b
+        let mut preprocessor = preprocessor(
+            &[
+                vec![a.invert(), b],
+                vec![a, b.invert()],
+                vec![a, b],
+                vec![a.invert(), b.invert()],
+            ],
+            2,
+        );
+        preprocessor.substitute_equivalences();
+        assert!(preprocessor.unsatisfiable);
+    }
+}
@@ -0,0 +1,201 @@
+// The `preprocess` module simplifies the clauses before the search starts. Everything it
+// removes goes on a reconstruction stack, so that models can be extended back to the
+// original clauses.
+mod blocked;
+mod eliminate;
+mod equivalence;
+mod pure;
+mod reconstruction;
+mod subsume;
+
+pub(crate) use reconstruction::Reconstruction;
+
+use log::info;
+
+use crate::instance::*;
+use crate::solver::subsume::signature;
+
+// Caps the literals each of the costlier passes looks at, so huge problems do not stall
+const PASS_BUDGET: usize = 20_000_000;
+
+/// The clauses being simplified, with a list of the clauses each literal occurs in
+pub(crate) struct Preprocessor {
+    // Removed clauses are left empty, so that clause indices stay stable
+    clauses: Vec<Vec<Literal>>,
+    removed: Vec<bool>,
+    signatures: Vec<u64>,
+    // Indexed by Literal::idx. Removed clauses are dropped lazily
+    occurrences: Vec<Vec<usize>>,
+    reconstruction: Reconstruction,
+    unsatisfiable: bool,
+}
+
+impl Preprocessor {
+    pub(crate) fn new(clauses: &[Clause], variable_count: usize) -> Preprocessor {
+        let mut preprocessor = Preprocessor {
+            clauses: vec![],
+            removed: vec![],
+            signatures: vec![],
+            occurrences: vec![vec![]; 2 * variable_count],
+            reconstruction: Reconstruction::new(variable_count),
+            unsatisfiable: false,
+        };
+        for clause in clauses {
+            preprocessor.add_clause(clause.literals().clone());
+        }
+        preprocessor
+    }
+
+    /// Simplifies the clauses, returning what is left of them and how to extend their models.
+    /// None if the clauses turned out to be unsatisfiable.
+    pub(crate) fn run(mut self) -> Option<(Vec<Clause>, Reconstruction)> {
+        let before = self.clause_count();
+        self.eliminate_pure_literals();
+        self.substitute_equivalences();
+        self.subsume();
+        self.eliminate_blocked_clauses();
+        self.eliminate_variables();
+        // Removing and replacing clauses can leave more variables pure
+        self.eliminate_pure_literals();
+        // Elimination adds resolvents, which may be binary clauses giving new equivalences,
+        // and may subsume or be subsumed by what is left
+        self.substitute_equivalences();
+        self.subsume();
+        self.eliminate_pure_literals();
+        if self.unsatisfiable {
+            info!("preprocessing found the clauses unsatisfiable");
+            return None;
+        }
+        info!(
+            "preprocessing left {} of {} clauses",
+            self.clause_count(),
+            before
+        );
+
+        let clauses = self
+            .clauses
+            .into_iter()
+            .zip(self.removed)
+            .filter(|(_, removed)| !removed)
+            .enumerate()
+            .map(|(ix, (literals, _))| Clause::new_with_id(ix, &literals))
+            .collect();
+        Some((clauses, self.reconstruction))
+    }
+
+    fn clause_count(&self) -> usize {
+        self.removed.iter().filter(|&&removed| !removed).count()
+    }
+
+    fn variable_count(&self) -> usize {
+        self.occurrences.len() / 2
+    }
+
+    /// Adds a clause, which must be sorted and free of duplicates and tautologies
+    fn add_clause(&mut self, literals: Vec<Literal>) -> usize {
+        if literals.is_empty() {
+            self.unsatisfiable = true;
+        }
+        let ix = self.clauses.len();
+        for lit in literals.iter() {
+            self.occurrences[lit.idx()].push(ix);
+        }
+        self.signatures.push(signature(&literals));
+        self.clauses.push(literals);
+        self.removed.push(false);
+        ix
+    }
+
+    /// Removes a clause, which the witness can be made true to satisfy when extending a model
+    fn remove_clause(&mut self, ix: usize, witness: Literal) {
+        let literals = self.drop_clause(ix);
+        self.reconstruction.push(witness, literals);
+    }
+
+    /// Removes a clause that the remaining clauses imply, so models need no fixing up for it
+    fn drop_clause(&mut self, ix: usize) -> Vec<Literal> {
+        self.removed[ix] = true;
+        std::mem::take(&mut self.clauses[ix])
+    }
+
+    /// The clauses the literal occurs in, dropping any that were removed
+    fn occurrences(&mut self, lit: Literal) -> &[usize] {
+        let removed = &self.removed;
+        self.occurrences[lit.idx()].retain(|&ix| !removed[ix]);
+        &self.occurrences[lit.idx()]
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        instance::*,
+        solver::{assignment_set::LiteralSet, preprocess::Preprocessor, rng::Rng, test::clauses},
+    };
+
+    pub(super) fn preprocessor(problem: &[Vec<Literal>], variable_count: usize) -> Preprocessor {
+        Preprocessor::new(&clauses(problem), variable_count)
+    }
+
+    fn satisfies(clauses: &[Clause], assignment: &LiteralSet) -> bool {
+        clauses.iter().all(|clause| {
+            clause
+                .literals()
+                .iter()
+                .any(|&lit| assignment.contains(lit))
+        })
+    }
+
+    /// A model of the clauses, by brute force
+    fn find_model(clauses: &[Clause], variable_count: usize) -> Option<LiteralSet> {
+        (0..1u64 << variable_count)
+            .map(|bits| {
+                let literals = (0..variable_count)
+                    .map(|ix| Literal::new(Variable(ix as u64), bits & (1 << ix) != 0))
+                    .collect::<Vec<_>>();
+                LiteralSet::from_assignment_vec(&literals)
+            })
+            .find(|assignment| satisfies(clauses, assignment))
+    }
+
+    #[test]
+    fn test_preprocessing_preserves_satisfiability() {
+        let mut rng = Rng::new(17);
+        for _ in 0..300 {
+            let variable_count = 2 + (rng.next_u64() % 9) as usize;
+            let clause_count = rng.next_u64() % (4 * variable_count as u64);
+            let clauses = (0..clause_count)
+                .map(|ix| {
+                    let mut literals = (0..1 + rng.next_u64() % 4)
+                        .map(|_| {
+                            let var = Variable(rng.next_u64() % variable_count as u64);
+                            Literal::new(var, rng.next_bool())
+                        })
+                        .collect::<Vec<_>>();
+                    literals.sort();
+                    literals.dedup_by_key(|lit| lit.var());
+                    Clause::new_with_id(ix as usize, &literals)
+                })
+                .collect::<Vec<_>>();
+
+            let expected = find_model(&clauses, variable_count).is_some();
+            match Preprocessor::new(&clauses, variable_count).run() {
+                None => assert!(!expected, "{:?} is satisfiable", clauses),
+                Some((simplified, reconstruction)) => {
+                    match find_model(&simplified, variable_count) {
+                        None => assert!(!expected, "{:?} is satisfiable", clauses),
+                        Some(mut assignment) => {
+                            reconstruction.extend(&mut assignment);
+                            assert!(
+                                satisfies(&clauses, &assignment),
+                                "{:?} not satisfied by {:?}",
+                                clauses,
+                                assignment
+                            );
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,82 @@
+use std::collections::VecDeque;
+
+use crate::instance::*;
+
+use super::Preprocessor;
+
+impl Preprocessor {
+    /// Pure literal elimination. A variable that occurs in only one polarity can be given that
+    /// polarity, which satisfies every clause it occurs in, so those clauses go, and the variable
+    /// with them. It is assigned when extending a model. Removing the clauses can leave other
+    /// variables pure, so we go on until none are.
+    pub(super) fn eliminate_pure_literals(&mut self) {
+        let mut queued = vec![true; self.variable_count()];
+        let mut queue = (0..self.variable_count() as u64)
+            .map(Variable)
+            .collect::<VecDeque<_>>();
+
+        let mut eliminated = 0;
+        while let Some(var) = queue.pop_front() {
+            queued[var.idx()] = false;
+            if self.reconstruction.is_eliminated(var) {
+                continue;
+            }
+            let positive = Literal::new(var, true);
+            let pure = match (
+                self.occurrences(positive).is_empty(),
+                self.occurrences(positive.invert()).is_empty(),
+            ) {
+                (false, true) => positive,
+                (true, false) => positive.invert(),
+                // Variables in no clauses are left to the search
+                _ => continue,
+            };
+
+            eliminated += 1;
+            for ix in self.occurrences(pure).to_vec() {
+                let literals = self.clauses[ix].clone();
+                self.remove_clause(ix, pure);
+                for lit in literals {
+                    if !queued[lit.var().idx()] {
+                        queued[lit.var().idx()] = true;
+                        queue.push_back(lit.var());
+                    }
+                }
+            }
+            self.reconstruction.eliminate(var);
+        }
+        log::info!("eliminated {} pure literals", eliminated);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::solver::{
+        assignment_set::LiteralSet, preprocess::test::preprocessor, test::literals,
+    };
+
+    #[test]
+    fn test_eliminate_pure_literals() {
+        let [a, b, c] = literals();
+        // a is pure, and once its clauses go, so is c
+        let mut preprocessor = preprocessor(
+            &[
+                vec![a, b],
+                vec![a, c.invert()],
+                vec![b.invert(), c],
+                vec![b, c],
+            ],
+            3,
+        );
+        preprocessor.eliminate_pure_literals();
+        assert!(preprocessor.removed.iter().all(|&removed| removed));
+        assert!(preprocessor.reconstruction.is_eliminated(a.var()));
+        assert!(preprocessor.reconstruction.is_eliminated(c.var()));
+        assert!(!preprocessor.reconstruction.is_eliminated(b.var()));
+
+        let mut assignment = LiteralSet::from_assignment_vec(&vec![b.invert()]);
+        preprocessor.reconstruction.extend(&mut assignment);
+        assert!(assignment.contains(a));
+        assert!(assignment.contains(c));
+    }
+}
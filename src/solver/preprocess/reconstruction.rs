@@ -0,0 +1,138 @@
+use fnv::FnvHashSet;
+
+use crate::instance::*;
+use crate::solver::assignment_set::LiteralSet;
+
+/// The clauses preprocessing removed, each with the literal that can be made true to satisfy
+/// it. A model of what is left extends to a model of the original clauses by going through
+/// them from the most recently removed, flipping the witness of any clause left unsatisfied.
+#[derive(Debug, Clone)]
+pub(crate) struct Reconstruction {
+    stack: Vec<(Literal, Vec<Literal>)>,
+    // Indexed by Variable::idx: whether the variable no longer appears in any clause, so need
+    // not be decided, and how many removed clauses it is the witness of
+    eliminated: Vec<bool>,
+    witness_counts: Vec<usize>,
+}
+
+impl Reconstruction {
+    pub(crate) fn new(variable_count: usize) -> Reconstruction {
+        Reconstruction {
+            stack: vec![],
+            eliminated: vec![false; variable_count],
+            witness_counts: vec![0; variable_count],
+        }
+    }
+
+    pub(crate) fn add_variable(&mut self, var: Variable) {
+        assert_eq!(var.idx(), self.eliminated.len());
+        self.eliminated.push(false);
+        self.witness_counts.push(0);
+    }
+
+    /// Records a clause removed from the problem, which the witness satisfies
+    pub(crate) fn push(&mut self, witness: Literal, clause: Vec<Literal>) {
+        debug_assert!(clause.contains(&witness));
+        self.witness_counts[witness.var().idx()] += 1;
+        self.stack.push((witness, clause));
+    }
+
+    /// Marks a variable whose clauses have all been removed
+    pub(crate) fn eliminate(&mut self, var: Variable) {
+        self.eliminated[var.idx()] = true;
+    }
+
+    pub(crate) fn is_eliminated(&self, var: Variable) -> bool {
+        self.eliminated[var.idx()]
+    }
+
+    /// Whether any removed clause could change the value of the variable
+    fn is_witness(&self, var: Variable) -> bool {
+        self.witness_counts[var.idx()] > 0
+    }
+
+    /// Extends a model of the preprocessed clauses to one of the original clauses
+    pub(crate) fn extend(&self, assignment: &mut LiteralSet) {
+        // Eliminated variables can take any value to start with. They need one before we go
+        // through the stack, or a clause could count as unsatisfied when it is not
+        for ix in 0..self.eliminated.len() {
+            let var = Variable(ix as u64);
+            if (self.is_eliminated(var) || self.is_witness(var)) && !assignment.contains_var(var) {
+                assignment.add(Literal::new(var, false));
+            }
+        }
+        for (witness, clause) in self.stack.iter().rev() {
+            let satisfied = clause.iter().any(|&lit| assignment.contains(lit));
+            if !satisfied {
+                assignment.add(*witness);
+            }
+        }
+    }
+
+    /// Undoes the preprocessing of the variables, so that they can be constrained again: by
+    /// new clauses, or assumptions. Returns the removed clauses that have to be put back.
+    /// Putting a clause back can drag in other variables whose values reconstruction would
+    /// have changed, so we go on until none are left.
+    pub(crate) fn restore(&mut self, variables: &[Variable]) -> Vec<Vec<Literal>> {
+        let mut touched = variables
+            .iter()
+            .copied()
+            .filter(|&var| self.is_witness(var) || self.is_eliminated(var))
+            .collect::<FnvHashSet<_>>();
+        let mut restored = vec![];
+        while !touched.is_empty() {
+            for var in touched.iter() {
+                self.eliminated[var.idx()] = false;
+                self.witness_counts[var.idx()] = 0;
+            }
+            let (now_restored, kept): (Vec<_>, Vec<_>) = self
+                .stack
+                .drain(..)
+                .partition(|(witness, _)| touched.contains(&witness.var()));
+            self.stack = kept;
+            touched = now_restored
+                .iter()
+                .flat_map(|(_, clause)| clause.iter())
+                .map(|lit| lit.var())
+                .filter(|&var| self.is_witness(var) || self.is_eliminated(var))
+                .collect();
+            restored.extend(now_restored.into_iter().map(|(_, clause)| clause));
+        }
+        restored
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        instance::*,
+        solver::{assignment_set::LiteralSet, preprocess::Reconstruction},
+    };
+
+    #[test]
+    fn test_extend_and_restore() {
+        let (a, b, c) = (
+            Literal::new(Variable(0), true),
+            Literal::new(Variable(1), true),
+            Literal::new(Variable(2), true),
+        );
+        // Eliminating b from (a || b), (!b || c) leaves (a || c)
+        let mut reconstruction = Reconstruction::new(3);
+        reconstruction.push(b, vec![a, b]);
+        reconstruction.push(b.invert(), vec![b.invert(), c]);
+        reconstruction.eliminate(b.var());
+
+        let mut assignment = LiteralSet::from_assignment_vec(&vec![a.invert(), c]);
+        reconstruction.extend(&mut assignment);
+        assert!(assignment.contains(b));
+        let mut assignment = LiteralSet::from_assignment_vec(&vec![a, c.invert()]);
+        reconstruction.extend(&mut assignment);
+        assert!(assignment.contains(b.invert()));
+
+        assert!(reconstruction.restore(&[a.var()]).is_empty());
+        let mut restored = reconstruction.restore(&[b.var()]);
+        restored.sort();
+        assert_eq!(restored, vec![vec![a, b], vec![b.invert(), c]]);
+        assert!(!reconstruction.is_eliminated(b.var()));
+    }
+}
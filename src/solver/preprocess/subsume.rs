@@ -0,0 +1,92 @@
+use std::collections::VecDeque;
+
+use crate::instance::*;
+use crate::solver::subsume::{signature, subsumes, Subsumes};
+
+use super::Preprocessor;
+
+impl Preprocessor {
+    /// Removes the clauses other clauses subsume, and strengthens clauses by self-subsuming
+    /// resolution. Each clause is checked against the clauses containing its least common
+    /// variable, and strengthened clauses are checked again, as they may now subsume more.
+    pub(super) fn subsume(&mut self) {
+        let (mut subsumed, mut strengthened) = (0, 0);
+        let mut queue = (0..self.clauses.len())
+            .filter(|&ix| !self.removed[ix])
+            .collect::<VecDeque<_>>();
+        while let Some(ix) = queue.pop_front() {
+            if self.removed[ix] || self.unsatisfiable {
+                continue;
+            }
+            let literals = self.clauses[ix].clone();
+            let sig = signature(&literals);
+            let Some(var) = literals.iter().map(|lit| lit.var()).min_by_key(|&var| {
+                self.occurrences[Literal::new(var, true).idx()].len()
+                    + self.occurrences[Literal::new(var, false).idx()].len()
+            }) else {
+                continue;
+            };
+            let mut candidates = self.occurrences(Literal::new(var, true)).to_vec();
+            candidates.extend_from_slice(self.occurrences(Literal::new(var, false)));
+
+            for other in candidates {
+                if other == ix || self.removed[other] || sig & !self.signatures[other] != 0 {
+                    continue;
+                }
+                match subsumes(&literals, &self.clauses[other]) {
+                    Subsumes::No => {}
+                    Subsumes::Subsumed => {
+                        subsumed += 1;
+                        self.drop_clause(other);
+                    }
+                    Subsumes::Strengthens(lit) => {
+                        strengthened += 1;
+                        let shorter = self.clauses[other]
+                            .iter()
+                            .copied()
+                            .filter(|&l| l != lit.invert())
+                            .collect();
+                        self.drop_clause(other);
+                        queue.push_back(self.add_clause(shorter));
+                    }
+                }
+            }
+        }
+        log::info!(
+            "subsumed {} clauses, strengthened {}",
+            subsumed,
+            strengthened
+        );
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::solver::{preprocess::test::preprocessor, test::literals};
+
+    #[test]
+    fn test_subsume() {
+        let [a, b, c, d] = literals();
+        let mut preprocessor = preprocessor(
+            &[
+                vec![a, b],
+                vec![a, b, c],
+                vec![a.invert(), b, d],
+                vec![b.invert(), c, d],
+            ],
+            4,
+        );
+        preprocessor.subsume();
+        // (a || b) strengthens (!a || b || d) to (b || d), which then strengthens
+        // (!b || c || d) to (c || d)
+        let mut remaining = preprocessor
+            .clauses
+            .iter()
+            .zip(preprocessor.removed.iter())
+            .filter(|(_, &removed)| !removed)
+            .map(|(literals, _)| literals.clone())
+            .collect::<Vec<_>>();
+        remaining.sort();
+        assert_eq!(remaining, vec![vec![a, b], vec![b, d], vec![c, d]]);
+    }
+}
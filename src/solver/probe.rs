@@ -0,0 +1,283 @@
+use fnv::FnvHashMap;
+use log::info;
+
+use crate::instance::*;
+
+use super::clause_store::{ClauseRef, ClauseRefResolver, ClauseStore};
+use super::knowledge_graph::KnowledgeGraph;
+use super::root::RootPropagator;
+use super::trail::Trail;
+
+/// Caps the hyper-binary resolvents added by a round of probing, so that they do not swamp the
+/// clauses we have
+const MAX_HYPER_BINARIES: usize = 1000;
+
+/// What a round of probing found
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct ProbeStats {
+    /// Variables taken from those we were asked to probe
+    pub(crate) probed: usize,
+    /// The last of them, so that the next round can carry on after it
+    pub(crate) last_probed: Option<Variable>,
+    /// Literals fixed at the root, as one polarity failed or both implied them
+    pub(crate) fixed: usize,
+    pub(crate) hyper_binaries: usize,
+    /// How many assignments the probes propagated
+    pub(crate) propagations: usize,
+    /// Probing derived the empty clause
+    pub(crate) unsatisfiable: bool,
+}
+
+/// Probes literals from the root: each is assumed on its own and propagated. A literal whose
+/// propagation conflicts is a failed literal, so its negation holds. Literals implied by both
+/// polarities of a variable hold whatever it is. Along the way, long clauses that become unit
+/// under the probe give hyper-binary resolvents, which imply the same in one step next time.
+pub(crate) struct Prober<'a> {
+    root: RootPropagator<'a>,
+    stats: ProbeStats,
+}
+
+/// The literals a probe implied, each with the clauses that derive it from the probe, if the
+/// proof needs them
+type Implied = FnvHashMap<Literal, Vec<ClauseRef>>;
+
+impl<'a> Prober<'a> {
+    pub(crate) fn new(
+        clause_store: &'a mut ClauseStore,
+        trail: &'a mut Trail,
+        knowledge_graph: &'a mut KnowledgeGraph,
+    ) -> Prober<'a> {
+        Prober {
+            root: RootPropagator::new(clause_store, trail, knowledge_graph),
+            stats: ProbeStats::default(),
+        }
+    }
+
+    /// Probes both polarities of the variables until the budget of propagations runs out. Must
+    /// be at the root. Leaves us at the root, with everything probing fixed propagated.
+    pub(crate) fn probe(
+        mut self,
+        variables: impl Iterator<Item = Variable>,
+        budget: usize,
+    ) -> ProbeStats {
+        assert_eq!(self.root.trail.current_decision_level(), 0);
+        if self.root.propagate() {
+            self.probe_variables(variables, budget);
+        }
+        self.stats.unsatisfiable = self.root.unsatisfiable;
+        info!("probing: {:?}", self.stats);
+        self.stats
+    }
+
+    fn probe_variables(&mut self, variables: impl Iterator<Item = Variable>, budget: usize) {
+        for var in variables {
+            if self.stats.propagations >= budget || self.root.unsatisfiable {
+                break;
+            }
+            self.stats.probed += 1;
+            self.stats.last_probed = Some(var);
+            if self.root.trail.assignment().contains_var(var) {
+                continue;
+            }
+            let positive = Literal::new(var, true);
+            let Some(implied_by_positive) = self.probe_literal(positive) else {
+                continue;
+            };
+            let Some(implied_by_negative) = self.probe_literal(positive.invert()) else {
+                continue;
+            };
+
+            for (&lit, positive_hints) in implied_by_positive.iter() {
+                let Some(negative_hints) = implied_by_negative.get(&lit) else {
+                    continue;
+                };
+                if self.root.trail.assignment().contains_var(lit.var()) {
+                    continue;
+                }
+                if !self.fix_implied(lit, positive, positive_hints, negative_hints) {
+                    break;
+                }
+            }
+        }
+    }
+
+    /// Assumes the literal and propagates it, returning what it implied. None if it failed,
+    /// in which case its negation has been fixed.
+    fn probe_literal(&mut self, literal: Literal) -> Option<Implied> {
+        let conflict = self.root.assume(literal);
+        let assigned = self.root.trail.assignments_since_last_decision();
+        self.stats.propagations += assigned;
+
+        if let Some(conflict) = conflict {
+            let hints = self
+                .root
+                .hints(conflict.conflicting_clause, &[literal.invert()]);
+            self.root.backtrack();
+            let clause =
+                self.root
+                    .clause_store
+                    .add_learnt_clause(vec![literal.invert()], 1, &hints);
+            self.fix_unit(clause);
+            return None;
+        }
+
+        let needs_hints = self.root.clause_store.proof_needs_hints();
+        let mut implied = Implied::default();
+        let mut hyper_binaries = vec![];
+        let inferred = self.root.trail.search_path()[1].inferred.clone();
+        for lit in inferred {
+            let reason = self.root.knowledge_graph.vertex(lit.var()).clause.unwrap();
+            let hints = match needs_hints {
+                true => self.root.hints(reason, &[literal.invert(), lit]),
+                false => vec![],
+            };
+            if self.stats.hyper_binaries + hyper_binaries.len() < MAX_HYPER_BINARIES
+                && self.is_hyper_binary(reason)
+            {
+                hyper_binaries.push((vec![lit, literal.invert()], hints.clone()));
+            }
+            implied.insert(lit, hints);
+        }
+        self.root.backtrack();
+
+        for (clause, hints) in hyper_binaries {
+            self.root.clause_store.add_learnt_clause(clause, 2, &hints);
+            self.stats.hyper_binaries += 1;
+        }
+        Some(implied)
+    }
+
+    /// Whether the reason of an implication under the probe has more than one of its literals
+    /// falsified by the probe. If so, no binary clause gives the implication directly.
+    fn is_hyper_binary(&self, reason: ClauseRef) -> bool {
+        if !matches!(reason, ClauseRef::Long { .. }) {
+            return false;
+        }
+        let store: &ClauseStore = self.root.clause_store;
+        store
+            .clause_literals(reason)
+            .filter(|lit| self.root.trail.find_decision_level(lit.var()) == Some(1))
+            .count()
+            > 2
+    }
+
+    /// Fixes a literal implied by both polarities of the variable, given the clauses that
+    /// derive each implication. Returns whether that left the problem satisfiable.
+    fn fix_implied(
+        &mut self,
+        literal: Literal,
+        positive: Literal,
+        positive_hints: &[ClauseRef],
+        negative_hints: &[ClauseRef],
+    ) -> bool {
+        if !self.root.clause_store.has_proof() {
+            return self.fix(literal, &[]);
+        }
+        // Resolving (!var || lit) and (var || lit) on var gives lit, and the proof has to be
+        // able to check each of them
+        let clause_store = &mut self.root.clause_store;
+        let positive_clause =
+            clause_store.add_learnt_clause(vec![literal, positive.invert()], 2, positive_hints);
+        let negative_clause =
+            clause_store.add_learnt_clause(vec![literal, positive], 2, negative_hints);
+        self.fix(literal, &[positive_clause, negative_clause])
+    }
+
+    /// Fixes the literal at the root, given the clauses that derive it. Returns whether that
+    /// left the problem satisfiable.
+    fn fix(&mut self, literal: Literal, hints: &[ClauseRef]) -> bool {
+        let clause = self
+            .root
+            .clause_store
+            .add_learnt_clause(vec![literal], 1, hints);
+        self.fix_unit(clause)
+    }
+
+    fn fix_unit(&mut self, clause: ClauseRef) -> bool {
+        self.stats.fixed += 1;
+        self.root.fix_unit(clause)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        instance::*,
+        solver::{
+            clause_store::ClauseStore,
+            knowledge_graph::KnowledgeGraph,
+            probe::Prober,
+            test::{clauses, literals},
+            trail::Trail,
+        },
+    };
+
+    fn probe(problem: &[Vec<Literal>], variable_count: usize) -> (Trail, ClauseStore, usize) {
+        let mut clause_store = ClauseStore::new(clauses(problem));
+        let mut trail = Trail::new();
+        let mut knowledge_graph = KnowledgeGraph::new(variable_count);
+        let stats = Prober::new(&mut clause_store, &mut trail, &mut knowledge_graph)
+            .probe((0..variable_count as u64).map(Variable), usize::MAX);
+        assert!(!stats.unsatisfiable);
+        (trail, clause_store, stats.hyper_binaries)
+    }
+
+    #[test]
+    fn test_failed_literal() {
+        let [a, b, c] = literals();
+        // a implies both b and !b
+        let (trail, _, _) = probe(&[vec![a.invert(), b], vec![a.invert(), b.invert()]], 3);
+        assert!(trail.assignment().contains(a.invert()));
+        assert!(!trail.assignment().contains_var(c.var()));
+        assert_eq!(trail.current_decision_level(), 0);
+    }
+
+    #[test]
+    fn test_implied_by_both_polarities() {
+        let [a, b, c] = literals();
+        // a implies b implies c, and !a implies c
+        let (trail, clause_store, _) =
+            probe(&[vec![a.invert(), b], vec![b.invert(), c], vec![a, c]], 3);
+        assert!(trail.assignment().contains(c));
+        assert!(!trail.assignment().contains_var(a.var()));
+        // Without a proof to check, c is fixed without the binary clauses that derive it
+        assert!(clause_store
+            .learnt_clauses()
+            .all(|(clause, _)| clause.len() < 2));
+    }
+
+    #[test]
+    fn test_last_probed() {
+        let [a, b, c, d] = literals();
+        let mut clause_store = ClauseStore::new(vec![Clause::new_with_id(0, &[a, b, c, d])]);
+        let mut trail = Trail::new();
+        let mut knowledge_graph = KnowledgeGraph::new(4);
+        // c is left out, as if eliminated. Each probe propagates only itself, so the budget
+        // runs out after d: two variables probed, but the next round has to carry on after d
+        let stats = Prober::new(&mut clause_store, &mut trail, &mut knowledge_graph)
+            .probe([b, d, a].map(|lit| lit.var()).into_iter(), 4);
+        assert_eq!(stats.probed, 2);
+        assert_eq!(stats.last_probed, Some(d.var()));
+    }
+
+    #[test]
+    fn test_hyper_binary_resolution() {
+        let [a, b, c, d] = literals();
+        // a implies b and c, which together imply d
+        let (_, clause_store, hyper_binaries) = probe(
+            &[
+                vec![a.invert(), b],
+                vec![a.invert(), c],
+                vec![b.invert(), c.invert(), d],
+            ],
+            4,
+        );
+        assert!(hyper_binaries > 0);
+        let store = &clause_store;
+        assert!(store.learnt_clauses().any(|(clause, _)| {
+            let mut literals = clause.literals(store).collect::<Vec<_>>();
+            literals.sort();
+            literals == vec![a.invert(), d]
+        }));
+    }
+}
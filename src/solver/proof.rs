@@ -0,0 +1,402 @@
+use std::fmt;
+use std::io::{self, BufWriter, Write};
+
+use crate::instance::Literal;
+
+/// How a proof of unsatisfiability is written out. Variables are numbered as in DIMACS, so
+/// Variable(0) is 1.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProofFormat {
+    /// DRAT, as text
+    Drat,
+    /// DRAT, in the compact binary encoding understood by drat-trim
+    BinaryDrat,
+    /// LRAT, as text. Each clause is given an ID, and lists the IDs of the clauses it was
+    /// derived from. The original clause with `Clause::id` n has ID n + 1, as in DIMACS.
+    Lrat,
+    /// LRAT, in the same binary encoding as DRAT
+    BinaryLrat,
+}
+
+impl ProofFormat {
+    fn is_binary(&self) -> bool {
+        matches!(self, ProofFormat::BinaryDrat | ProofFormat::BinaryLrat)
+    }
+
+    fn is_lrat(&self) -> bool {
+        matches!(self, ProofFormat::Lrat | ProofFormat::BinaryLrat)
+    }
+}
+
+/// Records the clauses the solver learns and deletes, so that an UNSAT answer can be checked
+/// against the original clauses by a tool such as drat-trim. Write errors are held on to until
+/// the proof is finished, so that they do not interrupt solving.
+pub(crate) struct Proof {
+    writer: BufWriter<Box<dyn Write + Send>>,
+    format: ProofFormat,
+    error: Option<io::Error>,
+    // LRAT deletions are labelled with the ID of the last clause added
+    last_id: u64,
+}
+
+impl fmt::Debug for Proof {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Proof")
+            .field("format", &self.format)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Proof {
+    pub(crate) fn new(writer: Box<dyn Write + Send>, format: ProofFormat) -> Proof {
+        Proof {
+            writer: BufWriter::new(writer),
+            format,
+            error: None,
+            last_id: 0,
+        }
+    }
+
+    /// Whether added clauses must come with the IDs of the clauses they were derived from
+    pub(crate) fn needs_hints(&self) -> bool {
+        self.format.is_lrat()
+    }
+
+    /// Records a clause derived by unit propagation from the clauses with the hinted IDs, in
+    /// the order they become unit
+    pub(crate) fn add(&mut self, id: u64, literals: &[Literal], hints: &[u64]) {
+        self.last_id = id;
+        let step = match self.format.is_lrat() {
+            true => Step::LratAdd(id, literals, hints),
+            false => Step::DratAdd(literals),
+        };
+        self.write_step(step);
+    }
+
+    pub(crate) fn delete(&mut self, id: u64, literals: &[Literal]) {
+        let step = match self.format.is_lrat() {
+            true => Step::LratDelete(self.last_id, id),
+            false => Step::DratDelete(literals),
+        };
+        self.write_step(step);
+    }
+
+    /// Flushes the proof, returning the first error hit while writing it
+    pub(crate) fn finish(&mut self) -> io::Result<()> {
+        if let Some(err) = self.error.take() {
+            return Err(err);
+        }
+        self.writer.flush()
+    }
+
+    fn write_step(&mut self, step: Step) {
+        if self.error.is_some() {
+            return;
+        }
+        let result = match self.format.is_binary() {
+            true => step.write_binary(&mut self.writer),
+            false => step.write_text(&mut self.writer),
+        };
+        if let Err(err) = result {
+            self.error = Some(err);
+        }
+    }
+}
+
+enum Step<'a> {
+    DratAdd(&'a [Literal]),
+    DratDelete(&'a [Literal]),
+    // The ID, literals, and hints
+    LratAdd(u64, &'a [Literal], &'a [u64]),
+    // The ID of the last clause added, and the ID of the clause to delete
+    LratDelete(u64, u64),
+}
+
+impl Step<'_> {
+    fn write_text(&self, writer: &mut impl Write) -> io::Result<()> {
+        match *self {
+            Step::DratAdd(literals) => write_text_literals(writer, literals),
+            Step::DratDelete(literals) => {
+                write!(writer, "d ")?;
+                write_text_literals(writer, literals)
+            }
+            Step::LratAdd(id, literals, hints) => {
+                write!(writer, "{} ", id)?;
+                for &lit in literals {
+                    write!(writer, "{} ", dimacs_literal(lit))?;
+                }
+                write!(writer, "0 ")?;
+                for hint in hints {
+                    write!(writer, "{} ", hint)?;
+                }
+                writeln!(writer, "0")
+            }
+            Step::LratDelete(last_id, id) => writeln!(writer, "{} d {} 0", last_id, id),
+        }
+    }
+
+    /// Each step starts with 'a' or 'd', and each number is written in a variable length
+    /// encoding, seven bits at a time. Literals are 2 * var + sign, and IDs 2 * id. Each list
+    /// of numbers ends with a zero byte.
+    fn write_binary(&self, writer: &mut impl Write) -> io::Result<()> {
+        match *self {
+            Step::DratAdd(literals) => {
+                writer.write_all(b"a")?;
+                write_binary_literals(writer, literals)
+            }
+            Step::DratDelete(literals) => {
+                writer.write_all(b"d")?;
+                write_binary_literals(writer, literals)
+            }
+            Step::LratAdd(id, literals, hints) => {
+                writer.write_all(b"a")?;
+                write_binary_number(writer, 2 * id)?;
+                write_binary_literals(writer, literals)?;
+                for &hint in hints {
+                    write_binary_number(writer, 2 * hint)?;
+                }
+                writer.write_all(&[0])
+            }
+            Step::LratDelete(_, id) => {
+                writer.write_all(b"d")?;
+                write_binary_number(writer, 2 * id)?;
+                writer.write_all(&[0])
+            }
+        }
+    }
+}
+
+/// The DIMACS encoding of the literal
+pub(crate) fn dimacs_literal(literal: Literal) -> i64 {
+    let var = literal.var().idx() as i64 + 1;
+    if literal.polarity() {
+        var
+    } else {
+        -var
+    }
+}
+
+fn write_text_literals(writer: &mut impl Write, literals: &[Literal]) -> io::Result<()> {
+    for &lit in literals {
+        write!(writer, "{} ", dimacs_literal(lit))?;
+    }
+    writeln!(writer, "0")
+}
+
+fn write_binary_literals(writer: &mut impl Write, literals: &[Literal]) -> io::Result<()> {
+    for &lit in literals {
+        write_binary_number(
+            writer,
+            2 * (lit.var().idx() as u64 + 1) + u64::from(!lit.polarity()),
+        )?;
+    }
+    writer.write_all(&[0])
+}
+
+fn write_binary_number(writer: &mut impl Write, mut number: u64) -> io::Result<()> {
+    while number > 127 {
+        writer.write_all(&[(number & 127) as u8 | 128])?;
+        number >>= 7;
+    }
+    writer.write_all(&[number as u8])
+}
+
+#[cfg(test)]
+pub(super) mod test {
+    use std::{collections::HashMap, fs};
+
+    use itertools::Itertools;
+
+    use crate::{
+        dimacs,
+        instance::{Literal, Variable},
+        solver::Instance,
+    };
+
+    use super::{Proof, ProofFormat};
+
+    fn write_proof(format: ProofFormat) -> Vec<u8> {
+        let file = tempfile::NamedTempFile::new().unwrap();
+        let mut proof = Proof::new(Box::new(file.reopen().unwrap()), format);
+        proof.add(
+            5,
+            &[
+                Literal::new(Variable(0), true),
+                Literal::new(Variable(1), false),
+            ],
+            &[1, 3],
+        );
+        proof.delete(4, &[Literal::new(Variable(99), false)]);
+        proof.add(6, &[], &[5, 2]);
+        proof.finish().unwrap();
+        fs::read(file.path()).unwrap()
+    }
+
+    #[test]
+    fn test_text_drat() {
+        assert_eq!(
+            String::from_utf8(write_proof(ProofFormat::Drat)).unwrap(),
+            "1 -2 0\nd -100 0\n0\n"
+        );
+    }
+
+    #[test]
+    fn test_binary_drat() {
+        assert_eq!(
+            write_proof(ProofFormat::BinaryDrat),
+            // -100 is 2 * 100 + 1 = 201, which takes two bytes
+            vec![b'a', 2, 5, 0, b'd', 201, 1, 0, b'a', 0]
+        );
+    }
+
+    #[test]
+    fn test_text_lrat() {
+        assert_eq!(
+            String::from_utf8(write_proof(ProofFormat::Lrat)).unwrap(),
+            "5 1 -2 0 1 3 0\n5 d 4 0\n6 0 5 2 0\n"
+        );
+    }
+
+    #[test]
+    fn test_binary_lrat() {
+        assert_eq!(
+            write_proof(ProofFormat::BinaryLrat),
+            vec![b'a', 10, 2, 5, 0, 2, 6, 0, b'd', 8, 0, b'a', 12, 0, 10, 4, 0]
+        );
+    }
+
+    pub(crate) fn dimacs_clauses(instance: &Instance) -> Vec<Vec<i64>> {
+        instance
+            .clauses
+            .iter()
+            .map(|c| {
+                c.literals()
+                    .iter()
+                    .map(|&l| super::dimacs_literal(l))
+                    .sorted()
+                    .collect_vec()
+            })
+            .collect_vec()
+    }
+
+    /// Checks each added clause is implied by unit propagation, which is all our proofs need
+    fn check_rup_proof(mut clauses: Vec<Vec<i64>>, proof: &str) -> bool {
+        let mut derived_empty = false;
+        for line in proof.lines() {
+            let mut words = line.split_whitespace().peekable();
+            let delete = words.next_if_eq(&"d").is_some();
+            let mut clause = words
+                .map(|w| w.parse::<i64>().unwrap())
+                .filter(|&l| l != 0)
+                .collect_vec();
+            clause.sort();
+            if delete {
+                let ix = clauses.iter().position(|c| *c == clause).unwrap();
+                clauses.swap_remove(ix);
+                continue;
+            }
+            // Assume the clause is false, and propagate to a conflict
+            let assignment = clause.iter().map(|l| -l).collect_vec();
+            if !propagates_to_conflict(&clauses, assignment) {
+                return false;
+            }
+            derived_empty |= clause.is_empty();
+            clauses.push(clause);
+        }
+        derived_empty
+    }
+
+    fn propagates_to_conflict(clauses: &[Vec<i64>], mut assignment: Vec<i64>) -> bool {
+        loop {
+            let mut changed = false;
+            for clause in clauses {
+                if clause.iter().any(|l| assignment.contains(l)) {
+                    continue;
+                }
+                let open = clause
+                    .iter()
+                    .filter(|&l| !assignment.contains(&-l))
+                    .collect_vec();
+                match open.len() {
+                    0 => return true,
+                    1 => {
+                        assignment.push(*open[0]);
+                        changed = true;
+                    }
+                    _ => {}
+                }
+            }
+            if !changed {
+                return false;
+            }
+        }
+    }
+
+    /// Checks that the hints of each added clause really do derive it by unit propagation
+    pub(crate) fn check_lrat_proof(clauses: Vec<Vec<i64>>, proof: &str) -> bool {
+        let mut by_id: HashMap<i64, Vec<i64>> = (1..).zip(clauses).collect();
+        let mut derived_empty = false;
+        for line in proof.lines() {
+            let words = line
+                .split_whitespace()
+                .filter(|&w| w != "d")
+                .map(|w| w.parse::<i64>().unwrap())
+                .collect_vec();
+            if line.contains('d') {
+                for id in words[1..words.len() - 1].iter() {
+                    by_id.remove(id).unwrap();
+                }
+                continue;
+            }
+            let mut parts = words[1..].split(|&w| w == 0);
+            let clause = parts.next().unwrap().to_vec();
+            let hints = parts.next().unwrap();
+            let mut assignment = clause.iter().map(|l| -l).collect_vec();
+            let mut conflict = false;
+            for hint in hints {
+                let open = by_id[hint]
+                    .iter()
+                    .filter(|&l| !assignment.contains(&-l))
+                    .collect_vec();
+                match open[..] {
+                    [] => {
+                        conflict = true;
+                        break;
+                    }
+                    [&unit] if !assignment.contains(&unit) => assignment.push(unit),
+                    _ => return false,
+                }
+            }
+            if !conflict {
+                return false;
+            }
+            derived_empty |= clause.is_empty();
+            by_id.insert(words[0], clause);
+        }
+        derived_empty
+    }
+
+    #[test]
+    fn test_unsat_proof_checks() {
+        let file = tempfile::NamedTempFile::new().unwrap();
+        let mut instance = dimacs::parse("examples/problem_specs/unsat/dubois20.cnf").unwrap();
+        instance.set_proof(file.reopen().unwrap(), ProofFormat::Drat);
+        assert!(instance.solve().model().is_none());
+        instance.finish_proof().unwrap();
+
+        let proof = fs::read_to_string(file.path()).unwrap();
+        assert!(check_rup_proof(dimacs_clauses(&instance), &proof));
+    }
+
+    #[test]
+    fn test_unsat_lrat_proof_checks() {
+        let file = tempfile::NamedTempFile::new().unwrap();
+        let mut instance = dimacs::parse("examples/problem_specs/unsat/dubois20.cnf").unwrap();
+        instance.set_proof(file.reopen().unwrap(), ProofFormat::Lrat);
+        assert!(instance.solve().model().is_none());
+        instance.finish_proof().unwrap();
+
+        let proof = fs::read_to_string(file.path()).unwrap();
+        assert!(check_lrat_proof(dimacs_clauses(&instance), &proof));
+    }
+}
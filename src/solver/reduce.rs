@@ -0,0 +1,147 @@
+use std::cmp::Ordering;
+
+const FIRST_REDUCTION: usize = 2000;
+const REDUCTION_INCREMENT: usize = 300;
+/// Clauses with an LBD this low are kept forever
+const GLUE_LBD: usize = 2;
+
+/// Which clause deletion policy the solver should use to keep the learnt clauses in check
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ClauseDeletion {
+    /// Delete the worst half of the learnt clauses, at growing intervals, as in Glucose
+    #[default]
+    Glucose,
+    /// Keep every clause learnt
+    Never,
+}
+
+impl ClauseDeletion {
+    pub(crate) fn policy(&self) -> Box<dyn ClauseDeletionPolicy> {
+        match *self {
+            ClauseDeletion::Glucose => Box::new(LearntClauseReduction::new()),
+            ClauseDeletion::Never => Box::new(NeverDelete {}),
+        }
+    }
+}
+
+/// A learnt clause, as a clause deletion policy sees it
+#[derive(Debug, Clone, PartialEq)]
+pub struct LearntClause {
+    pub len: usize,
+    /// The literal block distance of the clause when it was learnt
+    pub lbd: usize,
+    /// Bumped each time the clause takes part in a conflict, and decayed over time
+    pub activity: f64,
+    /// Whether the clause is the reason for a current assignment. These are never deleted
+    pub locked: bool,
+}
+
+/// Decides when the learnt clauses should be cut down, and which should go. Implement this to
+/// plug your own policy in with `SolverConfig::clause_deletion_policy`. Only learnt clauses of
+/// more than two literals are up for deletion: shorter ones are always kept
+pub trait ClauseDeletionPolicy: Send {
+    /// Whether the learnt clauses should be cut down, given the number of conflicts so far
+    fn due(&self, conflicts: usize) -> bool;
+
+    /// Picks the learnt clauses to delete, by their index in `learnts`. Locked clauses are
+    /// kept, even if picked
+    fn select(&mut self, learnts: &[LearntClause]) -> Vec<usize>;
+}
+
+pub(crate) struct NeverDelete {}
+
+impl ClauseDeletionPolicy for NeverDelete {
+    fn due(&self, _conflicts: usize) -> bool {
+        false
+    }
+
+    fn select(&mut self, _learnts: &[LearntClause]) -> Vec<usize> {
+        vec![]
+    }
+}
+
+/// Decides when the learnt clause database should be cut down, and which clauses should go.
+/// As in Glucose, the gap between reductions grows each time, and the worst half of the learnt
+/// clauses (by LBD, then by activity) are deleted.
+#[derive(Debug, Clone)]
+pub(crate) struct LearntClauseReduction {
+    next_reduction: usize,
+    interval: usize,
+}
+
+impl LearntClauseReduction {
+    pub(crate) fn new() -> LearntClauseReduction {
+        LearntClauseReduction {
+            next_reduction: FIRST_REDUCTION,
+            interval: FIRST_REDUCTION,
+        }
+    }
+}
+
+impl ClauseDeletionPolicy for LearntClauseReduction {
+    fn due(&self, conflicts: usize) -> bool {
+        conflicts >= self.next_reduction
+    }
+
+    /// Binary clauses, glue clauses, and locked clauses are never picked.
+    fn select(&mut self, learnts: &[LearntClause]) -> Vec<usize> {
+        self.interval += REDUCTION_INCREMENT;
+        self.next_reduction += self.interval;
+
+        let target = learnts.len() / 2;
+        let mut order = (0..learnts.len()).collect::<Vec<_>>();
+        // Worst first
+        order.sort_by(|&a, &b| {
+            let (a, b) = (&learnts[a], &learnts[b]);
+            b.lbd.cmp(&a.lbd).then(
+                a.activity
+                    .partial_cmp(&b.activity)
+                    .unwrap_or(Ordering::Equal),
+            )
+        });
+        order
+            .into_iter()
+            .filter(|&ix| {
+                let clause = &learnts[ix];
+                clause.len > 2 && clause.lbd > GLUE_LBD && !clause.locked
+            })
+            .take(target)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{ClauseDeletionPolicy, LearntClause, LearntClauseReduction};
+
+    #[test]
+    fn test_select_worst_half() {
+        let clause = |len, lbd, activity, locked| LearntClause {
+            len,
+            lbd,
+            activity,
+            locked,
+        };
+        let learnts = [
+            // Glue
+            clause(3, 2, 0.0, false),
+            // Active
+            clause(3, 3, 1.0, false),
+            // Inactive
+            clause(4, 3, 0.0, false),
+            // Bad
+            clause(3, 5, 0.0, false),
+            // Locked
+            clause(3, 6, 0.0, true),
+        ];
+
+        let mut reduction = LearntClauseReduction::new();
+        assert!(!reduction.due(0));
+        assert!(reduction.due(2000));
+        let mut selected = reduction.select(&learnts);
+        selected.sort();
+        // Half of the five clauses go, skipping over those we must keep
+        assert_eq!(selected, vec![2, 3]);
+        assert!(!reduction.due(2000));
+    }
+}
@@ -0,0 +1,204 @@
+use std::collections::VecDeque;
+
+/// Which restart policy the solver should use
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Restarts {
+    /// Never restart
+    Never,
+    /// Restart after `unit` times the next term of the Luby sequence (1, 1, 2, 1, 1, 2, 4, ...)
+    /// conflicts. A unit of zero is taken as one, as restarting without any conflicts in
+    /// between would never get anywhere
+    Luby { unit: usize },
+    /// Restart when the clauses learnt recently are of much worse quality (LBD) than usual, as
+    /// in Glucose
+    #[default]
+    Glucose,
+}
+
+impl Restarts {
+    pub(crate) fn policy(&self) -> Box<dyn RestartPolicy> {
+        match *self {
+            Restarts::Never => Box::new(NeverRestart {}),
+            Restarts::Luby { unit } => Box::new(LubyRestarts::new(unit)),
+            Restarts::Glucose => Box::new(GlucoseRestarts::new()),
+        }
+    }
+}
+
+/// Decides when the solver restarts. Implement this to plug your own policy in with
+/// `SolverConfig::restart_policy`
+pub trait RestartPolicy: Send {
+    /// Called after each conflict, with the LBD of the clause learnt from it
+    fn on_conflict(&mut self, lbd: usize);
+
+    /// Whether the solver should restart before its next decision
+    fn should_restart(&self) -> bool;
+
+    /// Called once the solver has restarted
+    fn restarted(&mut self);
+}
+
+pub(crate) struct NeverRestart {}
+
+impl RestartPolicy for NeverRestart {
+    fn on_conflict(&mut self, _lbd: usize) {}
+
+    fn should_restart(&self) -> bool {
+        false
+    }
+
+    fn restarted(&mut self) {}
+}
+
+pub(crate) struct LubyRestarts {
+    unit: usize,
+    restarts: usize,
+    conflicts: usize,
+}
+
+impl LubyRestarts {
+    pub(crate) fn new(unit: usize) -> LubyRestarts {
+        LubyRestarts {
+            unit: unit.max(1),
+            restarts: 0,
+            conflicts: 0,
+        }
+    }
+}
+
+impl RestartPolicy for LubyRestarts {
+    fn on_conflict(&mut self, _lbd: usize) {
+        self.conflicts += 1;
+    }
+
+    fn should_restart(&self) -> bool {
+        self.conflicts >= self.unit * luby(self.restarts)
+    }
+
+    fn restarted(&mut self) {
+        self.restarts += 1;
+        self.conflicts = 0;
+    }
+}
+
+/// The ix'th term (from 0) of the Luby sequence
+pub(crate) fn luby(mut ix: usize) -> usize {
+    // Find the finite subsequence containing ix, and its size
+    let mut size = 1;
+    let mut seq = 0;
+    while size < ix + 1 {
+        seq += 1;
+        size = 2 * size + 1;
+    }
+    while size - 1 != ix {
+        size = (size - 1) >> 1;
+        seq -= 1;
+        ix %= size;
+    }
+    1 << seq
+}
+
+const GLUCOSE_WINDOW: usize = 50;
+const GLUCOSE_MARGIN: f64 = 0.8;
+
+/// Restarts when the average LBD over a window of recent conflicts, scaled by a margin, is
+/// higher than the average over every conflict so far.
+pub(crate) struct GlucoseRestarts {
+    recent: VecDeque<usize>,
+    recent_sum: usize,
+    total_sum: usize,
+    total_count: usize,
+}
+
+impl GlucoseRestarts {
+    pub(crate) fn new() -> GlucoseRestarts {
+        GlucoseRestarts {
+            recent: VecDeque::with_capacity(GLUCOSE_WINDOW),
+            recent_sum: 0,
+            total_sum: 0,
+            total_count: 0,
+        }
+    }
+}
+
+impl RestartPolicy for GlucoseRestarts {
+    fn on_conflict(&mut self, lbd: usize) {
+        self.total_sum += lbd;
+        self.total_count += 1;
+        self.recent.push_back(lbd);
+        self.recent_sum += lbd;
+        if self.recent.len() > GLUCOSE_WINDOW {
+            self.recent_sum -= self.recent.pop_front().unwrap();
+        }
+    }
+
+    fn should_restart(&self) -> bool {
+        if self.recent.len() < GLUCOSE_WINDOW {
+            return false;
+        }
+        let recent_average = self.recent_sum as f64 / self.recent.len() as f64;
+        let total_average = self.total_sum as f64 / self.total_count as f64;
+        recent_average * GLUCOSE_MARGIN > total_average
+    }
+
+    fn restarted(&mut self) {
+        self.recent.clear();
+        self.recent_sum = 0;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use itertools::Itertools;
+
+    use super::*;
+
+    #[test]
+    fn test_luby_sequence() {
+        assert_eq!(
+            (0..15).map(luby).collect_vec(),
+            vec![1, 1, 2, 1, 1, 2, 4, 1, 1, 2, 1, 1, 2, 4, 8]
+        );
+    }
+
+    #[test]
+    fn test_luby_restarts() {
+        let mut policy = LubyRestarts::new(2);
+        let mut restart_after = vec![];
+        let mut conflicts = 0;
+        while restart_after.len() < 4 {
+            policy.on_conflict(1);
+            conflicts += 1;
+            if policy.should_restart() {
+                restart_after.push(conflicts);
+                conflicts = 0;
+                policy.restarted();
+            }
+        }
+        assert_eq!(restart_after, vec![2, 2, 4, 2]);
+    }
+
+    #[test]
+    fn test_luby_restarts_zero_unit() {
+        let mut policy = LubyRestarts::new(0);
+        assert!(!policy.should_restart());
+        policy.on_conflict(1);
+        assert!(policy.should_restart());
+    }
+
+    #[test]
+    fn test_glucose_restarts_on_worsening_lbd() {
+        let mut policy = GlucoseRestarts::new();
+        for _ in 0..(GLUCOSE_WINDOW * 4) {
+            policy.on_conflict(4);
+            assert!(!policy.should_restart());
+        }
+        // A run of much worse clauses should trigger a restart
+        for _ in 0..GLUCOSE_WINDOW {
+            policy.on_conflict(20);
+        }
+        assert!(policy.should_restart());
+        policy.restarted();
+        assert!(!policy.should_restart());
+    }
+}
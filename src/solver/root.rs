@@ -0,0 +1,112 @@
+use log::info;
+
+use crate::instance::*;
+
+use super::backtrack::{Conflict, ConflictAnalyzer};
+use super::clause_store::{ClauseRef, ClauseStore};
+use super::knowledge_graph::KnowledgeGraph;
+use super::trail::Trail;
+use super::unit_propagator::UnitPropagator;
+
+/// The search state, borrowed by the inprocessing that works from the root: probing and
+/// vivification assume literals, propagate them and undo them again, fixing at the root what
+/// they learn along the way.
+pub(crate) struct RootPropagator<'a> {
+    pub(crate) clause_store: &'a mut ClauseStore,
+    pub(crate) trail: &'a mut Trail,
+    pub(crate) knowledge_graph: &'a mut KnowledgeGraph,
+    /// Propagating at the root derived the empty clause
+    pub(crate) unsatisfiable: bool,
+}
+
+impl<'a> RootPropagator<'a> {
+    pub(crate) fn new(
+        clause_store: &'a mut ClauseStore,
+        trail: &'a mut Trail,
+        knowledge_graph: &'a mut KnowledgeGraph,
+    ) -> RootPropagator<'a> {
+        RootPropagator {
+            clause_store,
+            trail,
+            knowledge_graph,
+            unsatisfiable: false,
+        }
+    }
+
+    /// Assumes the literal at a new decision level and propagates it
+    pub(crate) fn assume(&mut self, literal: Literal) -> Option<Conflict> {
+        self.trail.add_decision(literal);
+        self.knowledge_graph.add_decision(literal);
+        UnitPropagator::new(self.clause_store, self.trail, self.knowledge_graph).propagate_units()
+    }
+
+    /// Fixes the literal of a unit clause at the root. Returns whether that left the problem
+    /// satisfiable.
+    pub(crate) fn fix_unit(&mut self, clause: ClauseRef) -> bool {
+        let literal = clause.unit();
+        self.trail.add_inferred(literal);
+        self.knowledge_graph.add_initial(literal);
+        self.propagate()
+    }
+
+    /// Propagates the assignments at the root. Returns whether that left the problem
+    /// satisfiable, recording the empty clause if not.
+    pub(crate) fn propagate(&mut self) -> bool {
+        let conflict = UnitPropagator::new(self.clause_store, self.trail, self.knowledge_graph)
+            .propagate_units();
+        match conflict {
+            None => true,
+            Some(Conflict {
+                conflicting_clause, ..
+            }) => {
+                info!("found a conflict at the root");
+                let hints = self.hints(conflicting_clause, &[]);
+                self.clause_store.learnt_empty_clause(&hints);
+                self.unsatisfiable = true;
+                false
+            }
+        }
+    }
+
+    pub(crate) fn hints(
+        &self,
+        conflicting_clause: ClauseRef,
+        clause: &[Literal],
+    ) -> Vec<ClauseRef> {
+        hints(
+            self.clause_store,
+            self.trail,
+            self.knowledge_graph,
+            conflicting_clause,
+            clause,
+        )
+    }
+
+    /// Undoes the assumptions, returning how many assignments that took back. The variables
+    /// they assigned stay in the decision heap, as it only drops them when it hands them out
+    pub(crate) fn backtrack(&mut self) -> usize {
+        let backtracked = self.trail.backtrack(0);
+        self.knowledge_graph.remove(&backtracked.assignments);
+        backtracked.assignments.len()
+    }
+}
+
+/// The clauses that derive the clause from the conflict, if the proof needs them
+pub(crate) fn hints(
+    clause_store: &ClauseStore,
+    trail: &Trail,
+    knowledge_graph: &KnowledgeGraph,
+    conflicting_clause: ClauseRef,
+    clause: &[Literal],
+) -> Vec<ClauseRef> {
+    if !clause_store.proof_needs_hints() {
+        return vec![];
+    }
+    ConflictAnalyzer::default().resolution_chain(
+        clause_store,
+        trail,
+        knowledge_graph,
+        conflicting_clause,
+        clause,
+    )
+}
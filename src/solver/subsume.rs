@@ -0,0 +1,195 @@
+use fnv::FnvHashSet;
+
+use crate::instance::*;
+
+use super::assignment_set::LiteralSet;
+use super::clause_store::{ClauseRef, ClauseStore};
+
+/// A bitset of the variables in a clause, folded into 64 bits. If a clause subsumes another,
+/// its signature is a subset of the other's, so most pairs can be ruled out without looking at
+/// their literals. Going by variable rather than literal keeps this true for strengthening.
+pub(crate) fn signature(literals: &[Literal]) -> u64 {
+    literals
+        .iter()
+        .fold(0, |sig, lit| sig | 1 << (lit.var().idx() % 64))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Subsumes {
+    No,
+    /// Every literal of the first clause is in the second, which can be dropped
+    Subsumed,
+    /// Every literal of the first clause but this one is in the second, which contains its
+    /// negation. Resolving the two gives the second clause without the negation.
+    Strengthens(Literal),
+}
+
+/// Whether the first clause subsumes the second, or can strengthen it by self-subsuming
+/// resolution. Both must be free of duplicates.
+pub(crate) fn subsumes(c: &[Literal], d: &[Literal]) -> Subsumes {
+    if c.len() > d.len() {
+        return Subsumes::No;
+    }
+    let mut flipped = None;
+    for &lit in c {
+        if d.contains(&lit) {
+            continue;
+        }
+        if flipped.is_none() && d.contains(&lit.invert()) {
+            flipped = Some(lit);
+            continue;
+        }
+        return Subsumes::No;
+    }
+    match flipped {
+        None => Subsumes::Subsumed,
+        Some(lit) => Subsumes::Strengthens(lit),
+    }
+}
+
+/// What subsumption found to do with the learnt clauses
+#[derive(Debug, Default)]
+pub(crate) struct LearntSubsumption {
+    /// Learnt clauses made redundant by another clause
+    pub(crate) subsumed: FnvHashSet<ClauseRef>,
+    pub(crate) strengthened: Vec<Strengthened>,
+}
+
+/// A learnt clause to replace with a shorter one
+#[derive(Debug, PartialEq)]
+pub(crate) struct Strengthened {
+    pub(crate) clause: ClauseRef,
+    /// The clause that resolves with it to give the shorter one
+    pub(crate) by: ClauseRef,
+    pub(crate) literals: Vec<Literal>,
+    pub(crate) lbd: usize,
+}
+
+/// Checks the long learnt clauses against every clause in the store, going from each clause to
+/// the learnt clauses sharing its least common variable. Must be at the root, where learnt
+/// clauses with assigned literals are left for the next time around.
+pub(crate) fn subsume_learnt_clauses(
+    store: &ClauseStore,
+    assignment: &LiteralSet,
+) -> LearntSubsumption {
+    let learnts = store
+        .learnt_clauses()
+        .filter(|(clause, _)| clause.len() > 2)
+        .map(|(clause, meta)| (clause, meta.lbd, clause.literals(store).collect::<Vec<_>>()))
+        .filter(|(_, _, literals)| literals.iter().all(|&l| !assignment.contains_var(l.var())))
+        .collect::<Vec<_>>();
+    let signatures = learnts
+        .iter()
+        .map(|(_, _, literals)| signature(literals))
+        .collect::<Vec<_>>();
+    // Indexed by Variable::idx, into the learnt clauses
+    let mut occurrences: Vec<Vec<usize>> = vec![];
+    for (ix, (_, _, literals)) in learnts.iter().enumerate() {
+        for lit in literals {
+            if occurrences.len() <= lit.var().idx() {
+                occurrences.resize(lit.var().idx() + 1, vec![]);
+            }
+            occurrences[lit.var().idx()].push(ix);
+        }
+    }
+
+    let mut result = LearntSubsumption::default();
+    let mut done = vec![false; learnts.len()];
+    for clause in store.iter() {
+        if result.subsumed.contains(&clause) {
+            continue;
+        }
+        let literals = clause.literals(store).collect::<Vec<_>>();
+        let sig = signature(&literals);
+        let Some(candidates) = literals
+            .iter()
+            .map(|lit| occurrences.get(lit.var().idx()).map_or(&[][..], |o| &o[..]))
+            .min_by_key(|candidates| candidates.len())
+        else {
+            continue;
+        };
+        for &ix in candidates {
+            let (target, lbd, target_literals) = &learnts[ix];
+            if done[ix] || *target == clause || sig & !signatures[ix] != 0 {
+                continue;
+            }
+            match subsumes(&literals, target_literals) {
+                Subsumes::No => {}
+                Subsumes::Subsumed => {
+                    done[ix] = true;
+                    result.subsumed.insert(*target);
+                }
+                Subsumes::Strengthens(lit) => {
+                    done[ix] = true;
+                    let literals = target_literals
+                        .iter()
+                        .copied()
+                        .filter(|&l| l != lit.invert())
+                        .collect::<Vec<_>>();
+                    result.strengthened.push(Strengthened {
+                        clause: *target,
+                        by: clause,
+                        lbd: (*lbd).min(literals.len()),
+                        literals,
+                    });
+                }
+            }
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        instance::*,
+        solver::{assignment_set::LiteralSet, clause_store::ClauseStore, test::literals},
+    };
+
+    use super::{signature, subsume_learnt_clauses, subsumes, Strengthened, Subsumes};
+
+    #[test]
+    fn test_subsumes() {
+        let [a, b, c, d] = literals();
+        assert_eq!(subsumes(&[a, b], &[a, b, c]), Subsumes::Subsumed);
+        assert_eq!(subsumes(&[a, b], &[a, b]), Subsumes::Subsumed);
+        assert_eq!(subsumes(&[a, b], &[a, c, d]), Subsumes::No);
+        assert_eq!(subsumes(&[a, b, c], &[a, b]), Subsumes::No);
+        assert_eq!(
+            subsumes(&[a, b.invert()], &[a, b, c]),
+            Subsumes::Strengthens(b.invert())
+        );
+        assert_eq!(
+            subsumes(&[a.invert(), b.invert()], &[a, b, c]),
+            Subsumes::No
+        );
+        assert_eq!(signature(&[a, c]) & !signature(&[a, b.invert(), c]), 0);
+    }
+
+    #[test]
+    fn test_subsume_learnt_clauses() {
+        let [a, b, c, d, e] = literals();
+        let mut store = ClauseStore::new(vec![Clause::new_with_id(0, &[a, b])]);
+        let original = store.iter().next().unwrap();
+        let subsumed = store.add_learnt_clause(vec![a, b, c], 3, &[]);
+        let strengthened = store.add_learnt_clause(vec![a.invert(), b, d], 3, &[]);
+        // Subsumed too, but left alone while e is assigned
+        store.add_learnt_clause(vec![a, b, e], 3, &[]);
+
+        let assignment = LiteralSet::from_assignment_vec(&vec![e.invert()]);
+        let result = subsume_learnt_clauses(&store, &assignment);
+        assert_eq!(
+            result.subsumed.into_iter().collect::<Vec<_>>(),
+            vec![subsumed]
+        );
+        assert_eq!(
+            result.strengthened,
+            vec![Strengthened {
+                clause: strengthened,
+                by: original,
+                literals: vec![b, d],
+                lbd: 2,
+            }]
+        );
+    }
+}
@@ -1,5 +1,4 @@
 use fnv::FnvHashMap;
-use lazy_static::lazy_static;
 use log::info;
 
 use crate::instance::*;
@@ -7,19 +6,22 @@ use core::fmt;
 
 use super::assignment_set::LiteralSet;
 
-lazy_static! {
-    static ref EMPTY_LIT_SET: LiteralSet = LiteralSet::new();
-}
-
 /// Stores the traversal path of the DFS. Should be the source of truth for what needs to be reverted upon backtrack.
 /// Note: we do not have a root node. An untraversed path has no trail
-
+///
+/// Strategies plugged into the solver get to look at it, but only the solver changes it.
 #[derive(Clone)]
-pub(crate) struct Trail {
+pub struct Trail {
     // Trail will never be empty - the first element stores decision level 0
     trail: Vec<TrailEntry>,
     cumulative_assignment: LiteralSet,
+    // The decision level each assigned variable was assigned at. Levels need not go up along
+    // the trail: chronological backtracking keeps literals implied below the current level
     decision_levels: FnvHashMap<Variable, usize>,
+    // Every assigned literal, in the order it was assigned
+    order: Vec<Literal>,
+    // How many of the literals in `order` have been handed out for propagation
+    propagated: usize,
 }
 
 impl Trail {
@@ -28,49 +30,77 @@ impl Trail {
             trail: vec![TrailEntry::new(None)],
             cumulative_assignment: LiteralSet::new(),
             decision_levels: FnvHashMap::default(),
+            order: vec![],
+            propagated: 0,
         }
     }
 
     /// The number of decisions in the current assignment
-    pub(crate) fn current_decision_level(&self) -> usize {
+    pub fn current_decision_level(&self) -> usize {
         self.trail.len() - 1
     }
 
+    /// The literal of the variable that is currently true, if the variable is assigned
+    pub fn value(&self, var: Variable) -> Option<Literal> {
+        self.cumulative_assignment.get(var)
+    }
+
     pub(crate) fn assignment(&self) -> &LiteralSet {
         &self.cumulative_assignment
     }
 
-    /// In the case of no decision being made prior to this function being called, we return the initial assignment set
-    pub(crate) fn assignments_since_last_decision(&self) -> &LiteralSet {
-        match self.trail.last() {
-            Some(entry) => &entry.all,
-            None => &EMPTY_LIT_SET,
-        }
+    /// How many literals are assigned at the current decision level, its decision included. In
+    /// the case of no decision being made, those of the initial assignment
+    pub(crate) fn assignments_since_last_decision(&self) -> usize {
+        self.trail
+            .last()
+            .map_or(0, |entry| entry.iter_literals().count())
     }
 
     pub(crate) fn last_decision(&self) -> Option<Literal> {
         self.trail.last().unwrap().decision
     }
 
+    /// The decision made at the decision level. None at the root
+    pub fn decision_at(&self, level: usize) -> Option<Literal> {
+        self.trail[level].decision
+    }
+
     // Records a step in the DFS search
     pub(crate) fn add_decision(&mut self, literal: Literal) {
         self.require_unset(literal);
 
         self.cumulative_assignment.add(literal);
-        self.trail.push(TrailEntry::new(Some(literal)));
+        let mut entry = TrailEntry::new(Some(literal));
+        entry.start = self.order.len();
+        self.trail.push(entry);
         self.decision_levels
             .insert(literal.var(), self.current_decision_level());
+        self.order.push(literal);
     }
 
     // Records an inferred assignment
     pub(crate) fn add_inferred(&mut self, literal: Literal) {
+        self.add_inferred_at(literal, self.current_decision_level());
+    }
+
+    /// Records an assignment inferred at a decision level at or below the current one, as the
+    /// literals implying it were all assigned by then
+    pub(crate) fn add_inferred_at(&mut self, literal: Literal, level: usize) {
         self.require_unset(literal);
 
         self.cumulative_assignment.add(literal);
-        if let Some(last_step) = self.trail.last_mut() {
-            last_step.inferred.push(literal);
-            last_step.all.add(literal);
-        }
+        self.decision_levels.insert(literal.var(), level);
+        let step = &mut self.trail[level];
+        step.inferred.push(literal);
+        self.order.push(literal);
+    }
+
+    /// Hands out the next assigned literal whose consequences have not been propagated yet
+    pub(crate) fn next_unpropagated(&mut self) -> Option<Literal> {
+        let literal = self.order.get(self.propagated).copied()?;
+        self.propagated += 1;
+        Some(literal)
     }
 
     /// Finds the last point to backtrack to according to the strategy (see find_backtrack_point_dfs)
@@ -81,8 +111,13 @@ impl Trail {
     pub(crate) fn backtrack(&mut self, pivot: usize) -> BacktrackResult {
         info!(
             "backtracking {} decision levels to {pivot}",
-            self.trail.len() - pivot - 1
+            self.trail.len().saturating_sub(pivot + 1)
         );
+        // Everything assigned before the first decision we drop is at or below the pivot
+        let start = self
+            .trail
+            .get(pivot + 1)
+            .map_or(self.order.len(), |e| e.start);
         let dropped = self.trail.drain(pivot + 1..).collect::<Vec<_>>();
         let last_decision = dropped.first().and_then(|e| e.decision);
         let mut assignments = vec![];
@@ -91,14 +126,25 @@ impl Trail {
             if let Some(chosen) = entry.decision {
                 assignments.push(chosen);
             }
-            if let Some(decision) = entry.decision {
-                self.decision_levels.remove(&decision.var());
-            }
         }
 
         for &literal in assignments.iter() {
             self.cumulative_assignment.remove(literal);
+            self.decision_levels.remove(&literal.var());
+        }
+        // Literals of the levels we keep can come after the first decision we drop. They stay,
+        // but are propagated again: the clauses they made unit may only have been satisfied by
+        // what we dropped. Everything before them was propagated before we moved past it
+        let mut kept = start;
+        for ix in start..self.order.len() {
+            let literal = self.order[ix];
+            if self.cumulative_assignment.contains(literal) {
+                self.order[kept] = literal;
+                kept += 1;
+            }
         }
+        self.order.truncate(kept);
+        self.propagated = self.propagated.min(start);
 
         BacktrackResult {
             assignments,
@@ -127,7 +173,8 @@ impl Trail {
         self.trail.iter()
     }
 
-    pub(crate) fn find_decision_level(&self, var: Variable) -> Option<usize> {
+    /// The decision level the variable was assigned at, if it is assigned
+    pub fn find_decision_level(&self, var: Variable) -> Option<usize> {
         self.decision_levels.get(&var).copied()
     }
 }
@@ -143,27 +190,36 @@ impl fmt::Debug for Trail {
     }
 }
 
+/// A decision level of the trail: its decision, and what was inferred there
 #[derive(Clone, Debug)]
-pub(crate) struct TrailEntry {
+pub struct TrailEntry {
     pub(crate) decision: Option<Literal>,
     pub(crate) inferred: Vec<Literal>,
-    pub(crate) all: LiteralSet,
+    // Where the decision is in the order literals were assigned
+    start: usize,
 }
 
 impl TrailEntry {
     fn new(literal: Option<Literal>) -> TrailEntry {
-        let mut ls = LiteralSet::new();
-        if let Some(lit) = literal {
-            ls.add(lit);
-        }
         TrailEntry {
             decision: literal,
             inferred: vec![],
-            all: ls,
+            start: 0,
         }
     }
 
-    /// Iterates over literals in the order they were added to the trail
+    /// The literal decided at this level. None at the root
+    pub fn decision(&self) -> Option<Literal> {
+        self.decision
+    }
+
+    /// The literals inferred at this level, in the order they were inferred
+    pub fn inferred(&self) -> &[Literal] {
+        &self.inferred
+    }
+
+    /// Iterates over the literals of the decision level in the order they were added to the
+    /// trail. Each is preceded by the literals of the level that implied it
     pub(crate) fn iter_literals(&self) -> impl DoubleEndedIterator<Item = &Literal> {
         std::iter::once(&self.decision)
             .filter_map(|x| x.as_ref())
@@ -184,6 +240,7 @@ mod test {
     use crate::solver::{
         backtrack::{Conflict, DumbBacktrackStrategy},
         clause_store::ClauseRef,
+        test::literals,
         trail::*,
     };
 
@@ -208,20 +265,46 @@ mod test {
         assert_eq!(sp.assignment().size(), 3);
     }
 
+    // Chronological backtracking keeps literals implied below the levels it drops, wherever
+    // they are on the trail, and propagates them again
+    #[test]
+    fn test_backtrack_keeps_lower_levels() {
+        let [a, b, c, d] = literals();
+
+        let mut trail = Trail::new();
+        trail.add_decision(a);
+        trail.add_decision(b);
+        trail.add_inferred(c);
+        trail.add_inferred_at(d, 1);
+        assert_eq!(trail.find_decision_level(c.var()), Some(2));
+        assert_eq!(trail.find_decision_level(d.var()), Some(1));
+        while trail.next_unpropagated().is_some() {}
+
+        let backtracked = trail.backtrack(1);
+        assert_eq!(backtracked.assignments, vec![c, b]);
+        assert_eq!(
+            trail.assignment(),
+            &LiteralSet::from_assignment_vec(&vec![a, d])
+        );
+        assert_eq!(trail.search_path()[1].inferred, vec![d]);
+        assert_eq!(trail.next_unpropagated(), Some(d));
+        assert_eq!(trail.next_unpropagated(), None);
+    }
+
     // Primarily tests that we are cleaning up the DFSPath assignments etc when we rollback
     #[test]
     fn test_backtrack_rollback() {
         let a = Variable(0);
-        let b = Variable(1);
+        let _b = Variable(1);
         let c = Variable(2);
         let notc = Literal::new(c, false);
 
         let mut path = Trail::new();
-        let strategy = DumbBacktrackStrategy {};
+        let _strategy = DumbBacktrackStrategy {};
 
         path.add_decision(Literal::new(a, true));
         path.add_inferred(notc);
-        let conflict = Conflict {
+        let _conflict = Conflict {
             conflicting_decision: None,
             conflicting_literal: notc,
             conflicting_clause: ClauseRef::Unit(notc),
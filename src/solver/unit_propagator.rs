@@ -1,12 +1,10 @@
-use std::collections::VecDeque;
-
 use crate::instance::*;
 
-use super::assignment_set::EvaluationResult;
 use super::backtrack::Conflict;
-use super::clause_store::{ClauseRef, ClauseStore};
+use super::clause_store::{ClauseRef, ClauseRefResolver, ClauseStore};
 use super::knowledge_graph::KnowledgeGraph;
 use super::trail::Trail;
+use super::watches::{Watch, WatchUpdate};
 use itertools::Itertools;
 
 pub(crate) struct UnitPropagator<'a> {
@@ -28,104 +26,105 @@ impl<'a> UnitPropagator<'a> {
         }
     }
 
-    pub(crate) fn evaluate(&'a self) -> Option<Conflict> {
-        // So we run through the untested literals, and check all of the relevant candidate clauses.
-        // If any of them are invalid, return the conflict
-        let untested_literals = self
-            .trail
-            .assignments_since_last_decision()
-            .as_assignment_vec();
-        for &literal in untested_literals.iter() {
-            for clause in self.clause_store.idx().find_evaluatable_candidates(literal) {
-                if self
-                    .trail
-                    .assignment()
-                    .evaluate(clause.literals(self.clause_store))
-                    == EvaluationResult::False
-                {
-                    return Some(Conflict {
-                        conflicting_decision: self.trail.last_decision(),
-                        conflicting_literal: literal,
-                        conflicting_clause: clause,
-                    });
-                }
-            }
-        }
-
-        None
-    }
-
+    /// Propagates every assignment on the trail that has not been propagated yet, stopping at
+    /// the first conflict.
     pub(crate) fn propagate_units(&mut self) -> Option<Conflict> {
-        let mut queue = VecDeque::new();
-        queue.extend(
-            self.trail
-                .assignments_since_last_decision()
-                .as_assignment_vec(),
-        );
-
-        while !queue.is_empty() {
-            let literal = queue.pop_back().unwrap();
-            // Build this list to avoid writing to the clause_index during the loop over borrowed clauses
-            let mut inferred_literals = vec![];
-            for clause in self.clause_store.idx().find_unit_prop_candidates(literal) {
-                match self.propagate_unit(literal, clause) {
-                    PropagationResult::Conflicted(conflict) => return Some(conflict),
-                    PropagationResult::Inferred(inferred) => {
-                        // Important: propagate_unit takes its assignment from trail. Deferring
-                        // adding to the dfs path causes issues
-                        self.trail.add_inferred(inferred);
-                        self.knowledge_graph.add_inferred(
-                            inferred,
-                            literal,
-                            self.trail.last_decision(),
-                            clause,
-                        );
-                        self.clause_store.mark_resolved(inferred.var());
-                        inferred_literals.push(inferred);
-                    }
-                    PropagationResult::Failed => (),
-                }
+        while let Some(literal) = self.trail.next_unpropagated() {
+            if let Some(conflict) = self.propagate_literal(literal) {
+                return Some(conflict);
             }
-            queue.extend(inferred_literals);
         }
         None
     }
 
-    fn propagate_unit(&self, literal: Literal, clause: ClauseRef) -> PropagationResult {
-        let assignment = self.trail.assignment();
+    /// Visits the clauses watching the negation of the newly assigned literal
+    fn propagate_literal(&mut self, literal: Literal) -> Option<Conflict> {
+        let false_literal = literal.invert();
+        let mut watches = self.clause_store.take_watches(false_literal);
+        let mut conflict = None;
+
+        // Walk the watch list, compacting the watches we keep to the front of it
+        let mut kept = 0;
+        let mut ix = 0;
+        while ix < watches.len() {
+            let watch = watches[ix];
+            ix += 1;
+            if self.trail.assignment().contains(watch.blocker) {
+                watches[kept] = watch;
+                kept += 1;
+                continue;
+            }
 
-        let mut last_free = None;
-        for literal in clause.literals(self.clause_store) {
-            if let Some(ass) = assignment.get(literal.var()) {
-                if ass == literal {
-                    // If there is a matching literal, we can't say anything about the free variable
-                    return PropagationResult::Failed;
+            let implied = match watch.clause {
+                // The blocker of a pair is the other literal, so we already know it's not true
+                ClauseRef::Pair(_, _) => watch.blocker,
+                ClauseRef::Long { .. } => match self.clause_store.update_watch(
+                    watch.clause,
+                    false_literal,
+                    self.trail.assignment(),
+                ) {
+                    WatchUpdate::Moved => continue,
+                    WatchUpdate::Satisfied(other) | WatchUpdate::Unit(other) => other,
+                },
+                ClauseRef::Unit(_) => unreachable!("unit clauses are never watched"),
+            };
+            watches[kept] = Watch {
+                clause: watch.clause,
+                blocker: implied,
+            };
+            kept += 1;
+
+            match self.trail.assignment().get(implied.var()) {
+                Some(value) if value == implied => {}
+                Some(_) => {
+                    conflict = Some(Conflict {
+                        conflicting_decision: self.trail.last_decision(),
+                        conflicting_literal: literal,
+                        conflicting_clause: watch.clause,
+                    });
+                    break;
                 }
-            } else {
-                if last_free.is_some() {
-                    // Implies we have multiple unresolved variables, short circuit
-                    return PropagationResult::Failed;
+                None => {
+                    let level = self.implication_level(literal, implied, watch.clause);
+                    self.trail.add_inferred_at(implied, level);
+                    self.knowledge_graph.add_inferred(
+                        implied,
+                        literal,
+                        self.trail.decision_at(level),
+                        watch.clause,
+                    );
                 }
-                last_free = Some(literal);
             }
         }
-        // Having no free variables, but being unable to propagate implies a conflict
-        match last_free {
-            Some(lit) => PropagationResult::Inferred(lit),
-            None => PropagationResult::Conflicted(Conflict {
-                conflicting_decision: self.trail.last_decision(),
-                conflicting_literal: literal,
-                conflicting_clause: clause,
-            }),
+
+        // Hang on to anything we didn't get to before a conflict
+        while ix < watches.len() {
+            watches[kept] = watches[ix];
+            kept += 1;
+            ix += 1;
         }
+        watches.truncate(kept);
+        self.clause_store.restore_watches(false_literal, watches);
+
+        conflict
     }
-}
 
-#[derive(Debug, Clone, PartialEq, Eq)]
-enum PropagationResult {
-    Conflicted(Conflict),
-    Inferred(Literal),
-    Failed,
+    /// The decision level the clause implies the literal at: the highest level of the rest of
+    /// its literals. That is the current level unless what triggered the implication is below
+    /// it, which happens once chronological backtracking has kept literals of lower levels.
+    fn implication_level(&self, trigger: Literal, implied: Literal, clause: ClauseRef) -> usize {
+        let current_level = self.trail.current_decision_level();
+        if self.trail.find_decision_level(trigger.var()) == Some(current_level) {
+            return current_level;
+        }
+        let store: &ClauseStore = self.clause_store;
+        store
+            .clause_literals(clause)
+            .filter(|&l| l != implied)
+            .filter_map(|l| self.trail.find_decision_level(l.var()))
+            .max()
+            .unwrap_or(0)
+    }
 }
 
 pub(crate) fn find_inital_assignment(clause_store: &ClauseStore) -> InitialAssignmentResult {
@@ -149,12 +148,10 @@ pub(crate) fn find_inital_assignment(clause_store: &ClauseStore) -> InitialAssig
 }
 
 pub(crate) fn record_initial_assignment(
-    clause_store: &mut ClauseStore,
     knowledge_graph: &mut KnowledgeGraph,
     assignment: &Vec<Literal>,
 ) {
     for &literal in assignment {
-        clause_store.mark_resolved(literal.var());
         knowledge_graph.add_initial(literal);
     }
 }
@@ -187,7 +184,7 @@ mod test {
         let b = Literal::new(vb, true);
 
         // a & !b
-        let clause = Clause::new(&vec![a, b.invert()]);
+        let clause = Clause::new(&[a, b.invert()]);
 
         let mut clause_store = ClauseStore::new(vec![clause]);
         trace!("store: {:?}", clause_store);
@@ -196,7 +193,6 @@ mod test {
 
         let decision = a.invert();
         trail.add_decision(decision);
-        clause_store.mark_resolved(decision.var());
         knowledge_graph.add_decision(decision);
 
         let mut unit_propagator =
@@ -218,9 +214,9 @@ mod test {
 
         // These two clauses will conflict when we try to propogate a=false
         // a | !b
-        let clause_one = Clause::new(&vec![Literal::new(a, true), Literal::new(b, false)]);
+        let clause_one = Clause::new(&[Literal::new(a, true), Literal::new(b, false)]);
         // a | b
-        let clause_two = Clause::new(&vec![Literal::new(a, true), Literal::new(b, false)]);
+        let clause_two = Clause::new(&[Literal::new(a, true), Literal::new(b, false)]);
         let clauses = vec![clause_one, clause_two];
 
         let mut clause_store = ClauseStore::new(clauses);
@@ -229,7 +225,6 @@ mod test {
 
         let decision = Literal::new(a, false);
         trail.add_decision(decision);
-        clause_store.mark_resolved(a);
         knowledge_graph.add_decision(decision);
 
         let mut unit_propagator =
@@ -239,4 +234,45 @@ mod test {
 
         assert_eq!(result, None);
     }
+
+    /// With `a | b | c` and `a | b | !c`, deciding !a and then !b infers c and then conflicts.
+    /// Backtracking must leave the watches usable for the next decision.
+    #[test]
+    fn test_unit_prop_long_clause_conflict_and_backtrack() {
+        let a = Literal::new(Variable(0), true);
+        let b = Literal::new(Variable(1), true);
+        let c = Literal::new(Variable(2), true);
+        let clauses = vec![Clause::new(&[a, b, c]), Clause::new(&[a, b, c.invert()])];
+
+        let mut clause_store = ClauseStore::new(clauses);
+        let mut trail = Trail::new();
+        let mut knowledge_graph = KnowledgeGraph::new(3);
+
+        trail.add_decision(a.invert());
+        knowledge_graph.add_decision(a.invert());
+        let result = UnitPropagator::new(&mut clause_store, &mut trail, &mut knowledge_graph)
+            .propagate_units();
+        assert_eq!(result, None);
+        assert_eq!(trail.assignment().size(), 1);
+
+        trail.add_decision(b.invert());
+        knowledge_graph.add_decision(b.invert());
+        let result = UnitPropagator::new(&mut clause_store, &mut trail, &mut knowledge_graph)
+            .propagate_units();
+        let conflict = result.expect("expected a conflict");
+        assert_eq!(conflict.conflicting_decision, Some(b.invert()));
+        assert!(trail.assignment().contains(c));
+
+        let backtracked = trail.backtrack(1);
+        knowledge_graph.remove(&backtracked.assignments);
+        trail.add_decision(b);
+        knowledge_graph.add_decision(b);
+        let result = UnitPropagator::new(&mut clause_store, &mut trail, &mut knowledge_graph)
+            .propagate_units();
+        assert_eq!(result, None);
+        assert_eq!(
+            trail.assignment(),
+            &LiteralSet::from_assignment_vec(&vec![a.invert(), b])
+        );
+    }
 }
@@ -0,0 +1,99 @@
+use fnv::FnvHashMap;
+use thiserror::Error;
+
+use crate::instance::*;
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum ModelError {
+    #[error("model assigns {0:?} both true and false")]
+    ContradictoryAssignment(Variable),
+    #[error("clause {clause} is not satisfied by the model: {literals:?}")]
+    UnsatisfiedClause {
+        clause: usize,
+        literals: Vec<Literal>,
+    },
+}
+
+/// Checks that the model satisfies every clause. A clause is only satisfied by a literal the
+/// model assigns true, so a partial model fails on any clause it leaves undecided.
+pub(crate) fn verify_model(clauses: &[Clause], model: &[Literal]) -> Result<(), ModelError> {
+    let mut values = FnvHashMap::default();
+    for lit in model {
+        if let Some(previous) = values.insert(lit.var(), lit.polarity()) {
+            if previous != lit.polarity() {
+                return Err(ModelError::ContradictoryAssignment(lit.var()));
+            }
+        }
+    }
+
+    for clause in clauses {
+        let satisfied = clause
+            .literals()
+            .iter()
+            .any(|lit| values.get(&lit.var()) == Some(&lit.polarity()));
+        if !satisfied {
+            return Err(ModelError::UnsatisfiedClause {
+                clause: clause.id(),
+                literals: clause.literals().clone(),
+            });
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        instance::*,
+        solver::verify::{verify_model, ModelError},
+    };
+
+    #[test]
+    fn test_verify_model() {
+        let (a, b, c) = (Variable(0), Variable(1), Variable(2));
+        let clauses = vec![
+            Clause::new_with_id(0, &[Literal::new(a, true), Literal::new(b, true)]),
+            Clause::new_with_id(1, &[Literal::new(b, false), Literal::new(c, true)]),
+        ];
+
+        let model = [
+            Literal::new(a, true),
+            Literal::new(b, false),
+            Literal::new(c, false),
+        ];
+        assert_eq!(verify_model(&clauses, &model), Ok(()));
+
+        let model = [
+            Literal::new(a, false),
+            Literal::new(b, true),
+            Literal::new(c, false),
+        ];
+        assert_eq!(
+            verify_model(&clauses, &model),
+            Err(ModelError::UnsatisfiedClause {
+                clause: 1,
+                literals: vec![Literal::new(b, false), Literal::new(c, true)],
+            })
+        );
+
+        // Leaving b undecided satisfies neither clause it appears in
+        let model = [Literal::new(a, false), Literal::new(c, true)];
+        assert_eq!(
+            verify_model(&clauses, &model),
+            Err(ModelError::UnsatisfiedClause {
+                clause: 0,
+                literals: vec![Literal::new(a, true), Literal::new(b, true)],
+            })
+        );
+
+        let model = [
+            Literal::new(a, true),
+            Literal::new(a, false),
+            Literal::new(c, true),
+        ];
+        assert_eq!(
+            verify_model(&clauses, &model),
+            Err(ModelError::ContradictoryAssignment(a))
+        );
+    }
+}
@@ -0,0 +1,213 @@
+use fnv::FnvHashSet;
+use itertools::Itertools;
+use log::info;
+
+use crate::instance::*;
+
+use super::clause_store::{ClauseRef, ClauseStore};
+use super::knowledge_graph::KnowledgeGraph;
+use super::root::RootPropagator;
+use super::trail::Trail;
+
+/// What a round of vivification did
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct VivifyStats {
+    /// Learnt clauses we tried to shorten
+    pub(crate) vivified: usize,
+    /// Learnt clauses replaced with a shorter clause
+    pub(crate) shortened: usize,
+    pub(crate) removed_literals: usize,
+    /// How many assignments vivifying propagated
+    pub(crate) propagations: usize,
+    /// Vivification derived the empty clause
+    pub(crate) unsatisfiable: bool,
+}
+
+/// Shortens learnt clauses by vivification. The literals of a clause are assumed false one at
+/// a time, each at a fresh decision level, and propagated. Literals that become false along
+/// the way are implied false by those before them, so are redundant. If a literal becomes
+/// true, or propagation conflicts, the literals assumed so far are enough for the clause.
+pub(crate) struct Vivifier<'a> {
+    root: RootPropagator<'a>,
+    stats: VivifyStats,
+    // The clauses replaced by shorter ones, to be deleted
+    replaced: FnvHashSet<ClauseRef>,
+}
+
+impl<'a> Vivifier<'a> {
+    pub(crate) fn new(
+        clause_store: &'a mut ClauseStore,
+        trail: &'a mut Trail,
+        knowledge_graph: &'a mut KnowledgeGraph,
+    ) -> Vivifier<'a> {
+        Vivifier {
+            root: RootPropagator::new(clause_store, trail, knowledge_graph),
+            stats: VivifyStats::default(),
+            replaced: FnvHashSet::default(),
+        }
+    }
+
+    /// Vivifies the long learnt clauses not yet vivified, those with the lowest LBD first,
+    /// until the budget of propagations runs out. Must be at the root, and leaves us there.
+    /// Returns the clauses that were replaced, which the caller should delete.
+    pub(crate) fn vivify(mut self, budget: usize) -> (VivifyStats, FnvHashSet<ClauseRef>) {
+        assert_eq!(self.root.trail.current_decision_level(), 0);
+        if !self.root.propagate() {
+            self.stats.unsatisfiable = true;
+            return (self.stats, self.replaced);
+        }
+        let candidates = self
+            .root
+            .clause_store
+            .learnt_clauses()
+            .filter(|(clause, meta)| clause.len() > 2 && !meta.vivified)
+            .map(|(clause, meta)| (clause, meta.lbd, meta.activity))
+            .sorted_by(|a, b| a.1.cmp(&b.1).then(b.2.total_cmp(&a.2)))
+            .map(|(clause, lbd, _)| (clause, lbd))
+            .collect_vec();
+
+        for (clause, lbd) in candidates {
+            if self.stats.propagations >= budget {
+                break;
+            }
+            let store: &ClauseStore = self.root.clause_store;
+            let literals = clause.literals(store).collect_vec();
+            // Literals fixed since the clause was learnt are better dealt with by subsumption
+            if literals
+                .iter()
+                .any(|lit| self.root.trail.assignment().contains_var(lit.var()))
+            {
+                continue;
+            }
+            self.root.clause_store.mark_vivified(clause);
+            self.stats.vivified += 1;
+            let Some((shorter, hints)) = self.vivify_clause(&literals) else {
+                continue;
+            };
+
+            self.stats.shortened += 1;
+            self.stats.removed_literals += literals.len() - shorter.len();
+            let lbd = lbd.min(shorter.len());
+            // Everything in the clause is unassigned at the root, so any literals can be watched
+            let shortened = self
+                .root
+                .clause_store
+                .add_learnt_clause(shorter, lbd, &hints);
+            self.root.clause_store.mark_vivified(shortened);
+            self.replaced.insert(clause);
+            if shortened.is_unit() && !self.root.fix_unit(shortened) {
+                break;
+            }
+        }
+        // Fixing units can make replaced clauses reasons at the root, so they have to stay
+        let reasons = self.root.knowledge_graph.reasons();
+        self.replaced.retain(|clause| !reasons.contains(clause));
+        self.stats.unsatisfiable = self.root.unsatisfiable;
+        info!("vivification: {:?}", self.stats);
+        (self.stats, self.replaced)
+    }
+
+    /// Assumes the negation of each literal of the clause in turn. Returns the literals the
+    /// clause can be shortened to, with the clauses that derive them, if any can be dropped.
+    fn vivify_clause(&mut self, literals: &[Literal]) -> Option<(Vec<Literal>, Vec<ClauseRef>)> {
+        let mut kept = vec![];
+        let mut hints = None;
+        for &lit in literals {
+            match self.root.trail.assignment().get(lit.var()) {
+                // Implied by the literals kept before it
+                Some(value) if value == lit => {
+                    kept.push(lit);
+                    let reason = self.root.knowledge_graph.vertex(lit.var()).clause.unwrap();
+                    hints = Some(self.root.hints(reason, &kept));
+                    break;
+                }
+                // Implied false by the literals kept before it
+                Some(_) => continue,
+                None => {}
+            }
+            kept.push(lit);
+            if let Some(conflict) = self.root.assume(lit.invert()) {
+                hints = Some(self.root.hints(conflict.conflicting_clause, &kept));
+                break;
+            }
+        }
+        self.stats.propagations += self.root.backtrack();
+
+        let hints = hints?;
+        if kept.len() == literals.len() {
+            return None;
+        }
+        Some((kept, hints))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use fnv::FnvHashSet;
+    use itertools::Itertools;
+
+    use crate::{
+        instance::*,
+        solver::{
+            clause_store::{ClauseRef, ClauseStore},
+            knowledge_graph::KnowledgeGraph,
+            test::{clauses, literals},
+            trail::Trail,
+            vivify::Vivifier,
+        },
+    };
+
+    /// Vivifies the learnt clause, returning the learnt clauses left and those replaced
+    fn vivify(
+        problem: &[Vec<Literal>],
+        learnt: Vec<Literal>,
+        variable_count: usize,
+    ) -> (Vec<Vec<Literal>>, FnvHashSet<ClauseRef>, ClauseRef) {
+        let mut clause_store = ClauseStore::new(clauses(problem));
+        let learnt = clause_store.add_learnt_clause(learnt, 3, &[]);
+        let mut trail = Trail::new();
+        let mut knowledge_graph = KnowledgeGraph::new(variable_count);
+        let (stats, replaced) =
+            Vivifier::new(&mut clause_store, &mut trail, &mut knowledge_graph).vivify(usize::MAX);
+        assert!(!stats.unsatisfiable);
+        assert_eq!(trail.current_decision_level(), 0);
+        let store = &clause_store;
+        let learnt_clauses = store
+            .learnt_clauses()
+            .map(|(clause, _)| clause.literals(store).sorted().collect_vec())
+            .collect_vec();
+        (learnt_clauses, replaced, learnt)
+    }
+
+    #[test]
+    fn test_vivify_implied_literal() {
+        let [a, b, c, d] = literals();
+        // a implies b implies c, so (!a || c || d) does not need d
+        let (learnt_clauses, replaced, learnt) = vivify(
+            &[vec![a.invert(), b], vec![b.invert(), c]],
+            vec![a.invert(), c, d],
+            4,
+        );
+        assert!(learnt_clauses.contains(&vec![a.invert(), c]));
+        assert_eq!(replaced.into_iter().collect_vec(), vec![learnt]);
+    }
+
+    #[test]
+    fn test_vivify_redundant_literal() {
+        let [a, b, c, d] = literals();
+        // !a implies !b, so b can go from (a || b || c || d)
+        let (learnt_clauses, replaced, learnt) =
+            vivify(&[vec![a, b.invert()]], vec![a, b, c, d], 4);
+        assert!(learnt_clauses.contains(&vec![a, c, d]));
+        assert_eq!(replaced.into_iter().collect_vec(), vec![learnt]);
+    }
+
+    #[test]
+    fn test_vivify_minimal_clause() {
+        let [a, b, c] = literals();
+        let (learnt_clauses, replaced, _) =
+            vivify(&[vec![a, b.invert()]], vec![a.invert(), b, c], 3);
+        assert_eq!(learnt_clauses, vec![vec![a.invert(), b, c]]);
+        assert!(replaced.is_empty());
+    }
+}
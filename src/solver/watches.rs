@@ -0,0 +1,66 @@
+use crate::instance::Literal;
+
+use super::clause_store::ClauseRef;
+
+/// An entry in a watch list. The blocker is another literal from the same clause: if it is
+/// already true, the clause is satisfied and we can skip looking at its literals entirely.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Watch {
+    pub(crate) clause: ClauseRef,
+    pub(crate) blocker: Literal,
+}
+
+/// Two watched literal index. Every clause with two or more literals watches two of them, and
+/// only needs to be looked at when one of its watched literals becomes false. As long as the
+/// watched literals are not false, the clause can be neither unit nor conflicting. Nothing in
+/// here needs to be touched on backtrack.
+///
+/// Long clauses keep their watched literals in the first two positions of the clause.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Watches {
+    // Indexed by Literal::idx. The clauses watching the given literal
+    by_literal: Vec<Vec<Watch>>,
+}
+
+impl Watches {
+    pub(crate) fn new() -> Watches {
+        Watches::default()
+    }
+
+    pub(crate) fn watch(&mut self, literal: Literal, watch: Watch) {
+        let ix = literal.idx();
+        if ix >= self.by_literal.len() {
+            self.by_literal.resize_with(ix + 1, Vec::new);
+        }
+        self.by_literal[ix].push(watch);
+    }
+
+    /// Removes the watch list of the literal, to allow it to be walked while the rest of the
+    /// index is updated. Must be handed back with `restore`.
+    pub(crate) fn take(&mut self, literal: Literal) -> Vec<Watch> {
+        match self.by_literal.get_mut(literal.idx()) {
+            Some(watches) => std::mem::take(watches),
+            None => vec![],
+        }
+    }
+
+    pub(crate) fn restore(&mut self, literal: Literal, watches: Vec<Watch>) {
+        if let Some(slot) = self.by_literal.get_mut(literal.idx()) {
+            *slot = watches;
+        } else if !watches.is_empty() {
+            panic!("restoring watches for unknown literal {:?}", literal);
+        }
+    }
+}
+
+/// The outcome of a watched literal of a long clause becoming false
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum WatchUpdate {
+    /// The watch was moved to another non-false literal
+    Moved,
+    /// The other watched literal is true, so the clause is satisfied
+    Satisfied(Literal),
+    /// Every unwatched literal is false. The clause is either unit on the other watched literal,
+    /// or conflicting if that is false too.
+    Unit(Literal),
+}
@@ -42,13 +42,6 @@ impl VariableRegister {
         Variable(ix)
     }
 
-    pub(crate) fn ensure_original(&mut self, name: &str) -> Variable {
-        match self.get_by_name(name) {
-            Some(var) => var,
-            None => self.create_original(name),
-        }
-    }
-
     pub(crate) fn create_tseitin(&mut self) -> Variable {
         let ix = self.literal_count;
         self.variables.push(Variable(ix));